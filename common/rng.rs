@@ -0,0 +1,44 @@
+// splitmix64, reproducible from a seed
+// written to deal the cards of the day 7 tournaments and shared with the day 12 sampler and
+// the day 14 state hash, each day includes this file with #[path] as the days are separate crates
+pub struct Rng(pub u64);
+
+const GAMMA: u64 = 0x9E3779B97F4A7C15;
+
+// the value a generator seeded with `x` gives first, a well spread hash of a single value
+pub fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(GAMMA);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        let z = mix(self.0);
+        self.0 = self.0.wrapping_add(GAMMA);
+        z
+    }
+
+    // uniform in 0..n, n must not be 0
+    pub fn below(&mut self, n: u128) -> u128 {
+        // reject the top end so every value is equally likely
+        // bounds which fit in 64 bits draw one value at a time, so those draws never change
+        if let Ok(n) = u64::try_from(n) {
+            let zone = u64::MAX - u64::MAX % n;
+            loop {
+                let x = self.next_u64();
+                if x < zone {
+                    return (x % n) as u128;
+                }
+            }
+        }
+        let zone = u128::MAX - u128::MAX % n;
+        loop {
+            let x = (self.next_u64() as u128) << 64 | self.next_u64() as u128;
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+}
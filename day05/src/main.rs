@@ -1,56 +1,130 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 type Mapping = (u64, u64, u64);
-type Category = Vec<Mapping>;
 type Seeds = Vec<u64>;
-type Almanac = (Seeds, [Category; 7]);
+
+#[derive(Debug, PartialEq, Clone)]
+struct Category {
+    source: String,
+    target: String,
+    mappings: Vec<Mapping>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Almanac {
+    seeds: Seeds,
+    categories: Vec<Category>,
+}
+
+#[derive(Debug, PartialEq)]
+enum ChainError {
+    // no sequence of maps leads from `from` to `to`
+    Broken { from: String, to: String },
+    // more than one sequence of maps leads from `from` to `to`
+    Ambiguous { from: String, to: String },
+}
 
 fn category_map(category: &Category, val: u64) -> u64{
     let mut mapping = None;
-    for m in category{
+    for m in &category.mappings{
         let (_, source_start, len) = *m;
         if source_start <= val && val <= source_start + len{
             // within mapping
-            mapping = Some(m); 
+            mapping = Some(m);
         }
     }
     if let Some((dest_start, source_start, _)) = mapping{
         let offset = val - source_start;
-        dest_start + offset 
+        dest_start + offset
     }else{
         val
     }
 }
 
-fn parse_input(input: &str) -> (Seeds, [Category; 7]) {
+fn parse_input(input: &str) -> Almanac {
     fn parse_seeds(line: &str) -> Seeds {
-        line.split(":").skip(1).next().unwrap().split(" ").filter(|s| s.len() > 0).map(|s| s.parse::<u64>().unwrap()).collect()
+        line.split(':').nth(1).unwrap().split(' ').filter(|s| !s.is_empty()).map(|s| s.parse::<u64>().unwrap()).collect()
     }
     fn parse_mapping(line: &str) -> Mapping {
-        let values: Vec<u64> = line.split(" ").filter(|s| s.len() > 0).map(|s| s.parse::<u64>().unwrap()).collect();
+        let values: Vec<u64> = line.split(' ').filter(|s| !s.is_empty()).map(|s| s.parse::<u64>().unwrap()).collect();
         (values[0], values[1], values[2])
     }
-    
-    let first_line = input.lines().take(1).next().unwrap();
-    let categories: Vec<Category> = input.split("\n\n").skip(1).map(|category_str| 
-        category_str.split(":").skip(1).next().unwrap().lines().filter(|s| s.len() > 0).map(|line| parse_mapping(line)).collect()
-    ).collect();
+    fn parse_category(category_str: &str) -> Category {
+        let (header, body) = category_str.split_once(':').unwrap();
+        // header looks like "seed-to-soil map"
+        let names = header.trim().trim_end_matches(" map");
+        let (source, target) = names.split_once("-to-").expect("Map header without \"-to-\"");
+        let mappings = body.lines().filter(|s| !s.is_empty()).map(parse_mapping).collect();
+        Category { source: source.to_string(), target: target.to_string(), mappings }
+    }
+
+    let first_line = input.lines().next().unwrap();
     let seeds: Seeds = parse_seeds(first_line);
-    input.split(":\n");
-    let mut a: [Category; 7] = Default::default();
-    a.clone_from_slice(&categories[0..7]);
-    (seeds, a)
+    let categories: Vec<Category> = input.split("\n\n").skip(1).map(parse_category).collect();
+    Almanac { seeds, categories }
 }
 
-fn solve_part1(almanac: &Almanac) -> u64 {
-    let mut results = vec![[0; 8]; almanac.0.len()];
-    for (i, &seed) in almanac.0.iter().enumerate() {
-        let vals = &mut results[i];
-        vals[0] = seed;
-        for (j, category) in almanac.1.iter().enumerate() {
-            vals[j+1] = category_map(&category, vals[j])
+impl Almanac {
+    // finds the maps which convert `from` into `to`, regardless of the order they appear in
+    fn chain(&self, from: &str, to: &str) -> Result<Vec<&Category>, ChainError> {
+        let mut graph: BTreeMap<&str, Vec<&Category>> = BTreeMap::new();
+        let mut sources: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for category in &self.categories {
+            graph.entry(category.source.as_str()).or_default().push(category);
+            sources.entry(category.target.as_str()).or_default().push(&category.source);
+        }
+        // the categories from which `to` can be reached at all, walking the maps backwards
+        let mut reaches_to = BTreeSet::from([to]);
+        let mut queue = vec![to];
+        while let Some(current) = queue.pop() {
+            for &source in sources.get(current).into_iter().flatten() {
+                if reaches_to.insert(source) {
+                    queue.push(source);
+                }
+            }
+        }
+
+        // depth first search over simple paths, so cycles in the almanac do not hang us
+        // a second path already makes the chain ambiguous, so the search stops there
+        // and it never enters a category from which `to` cannot be reached, which would be searched in vain
+        fn search<'a>(graph: &BTreeMap<&str, Vec<&'a Category>>, reaches_to: &BTreeSet<&str>, current: &str, to: &str, path: &mut Vec<&'a Category>, found: &mut Vec<Vec<&'a Category>>) {
+            if found.len() > 1 {
+                return;
+            }
+            if current == to {
+                found.push(path.clone());
+                return;
+            }
+            for &category in graph.get(current).into_iter().flatten() {
+                // every node on the path so far is either a source of a taken map or the current one
+                let visited = category.target == current || path.iter().any(|c| c.source == category.target);
+                if visited || !reaches_to.contains(category.target.as_str()) {
+                    continue;
+                }
+                path.push(category);
+                search(graph, reaches_to, &category.target, to, path, found);
+                path.pop();
+            }
+        }
+        let mut found = vec![];
+        search(&graph, &reaches_to, from, to, &mut vec![], &mut found);
+
+        match found.len() {
+            0 => Err(ChainError::Broken { from: from.to_string(), to: to.to_string() }),
+            1 => Ok(found.pop().unwrap()),
+            _ => Err(ChainError::Ambiguous { from: from.to_string(), to: to.to_string() }),
         }
-        //println!("Seed {}, soil {}, fertilizer {}, water {}, light {}, temperature {}, humidity {}, location {}", vals[0], vals[1], vals[2], vals[3], vals[4], vals[5], vals[6], vals[7]);      
     }
-    results.iter().map(|vals| vals[7]).min().unwrap()
+}
+
+fn chain_map(chain: &[&Category], val: u64) -> u64 {
+    chain.iter().fold(val, |v, category| category_map(category, v))
+}
+
+fn solve_part1(almanac: &Almanac) -> u64 {
+    // the chain is the same for every seed
+    let chain = almanac.chain("seed", "location").unwrap();
+    almanac.seeds.iter().map(|&seed| chain_map(&chain, seed)).min().unwrap()
 }
 
 fn main() {
@@ -64,7 +138,7 @@ fn main() {
 mod tests {
     use super::*;
 
-    const EXAMPLE: &'static str = 
+    const EXAMPLE: &str =
 "seeds: 79 14 55 13
 
 seed-to-soil map:
@@ -100,24 +174,27 @@ humidity-to-location map:
 56 93 4";
 
     macro_rules! example_almanac {
-        () => {
-            (vec![79, 14, 55, 13], 
-            [
-                vec![(50, 98,  2), (52, 50, 48)],
-                vec![( 0, 15, 37), (37, 52,  2), (39, 0, 15)],
-                vec![(49, 53,  8), ( 0, 11, 42), (42, 0, 7), (57, 7, 4)],
-                vec![(88, 18,  7), (18, 25, 70)],
-                vec![(45, 77, 23), (81, 45, 19), (68, 64, 13)],
-                vec![( 0, 69,  1), ( 1,  0, 69)],
-                vec![(60, 56, 37), (56, 93,  4)],
-            ])
-        };
+        () => {{
+            let category = |source: &str, target: &str, mappings: Vec<Mapping>| Category { source: source.to_string(), target: target.to_string(), mappings };
+            Almanac {
+                seeds: vec![79, 14, 55, 13],
+                categories: vec![
+                    category("seed", "soil", vec![(50, 98,  2), (52, 50, 48)]),
+                    category("soil", "fertilizer", vec![( 0, 15, 37), (37, 52,  2), (39, 0, 15)]),
+                    category("fertilizer", "water", vec![(49, 53,  8), ( 0, 11, 42), (42, 0, 7), (57, 7, 4)]),
+                    category("water", "light", vec![(88, 18,  7), (18, 25, 70)]),
+                    category("light", "temperature", vec![(45, 77, 23), (81, 45, 19), (68, 64, 13)]),
+                    category("temperature", "humidity", vec![( 0, 69,  1), ( 1,  0, 69)]),
+                    category("humidity", "location", vec![(60, 56, 37), (56, 93,  4)]),
+                ],
+            }
+        }};
     }
 
     #[test]
     fn test_parsing() {
         let almanac = example_almanac!();
-        assert_eq!(parse_input(&EXAMPLE), almanac);
+        assert_eq!(parse_input(EXAMPLE), almanac);
     }
 
     #[test]
@@ -125,4 +202,49 @@ humidity-to-location map:
         let almanac = example_almanac!();
         assert_eq!(solve_part1(&almanac), 35);
     }
+
+    #[test]
+    fn test_chain_any_order() {
+        let mut almanac = example_almanac!();
+        almanac.categories.reverse();
+        assert_eq!(solve_part1(&almanac), 35);
+        // seed 79 has humidity 78 in the puzzle description
+        assert_eq!(almanac.chain("seed", "humidity").map(|chain| chain_map(&chain, 79)), Ok(78));
+        assert_eq!(almanac.chain("water", "temperature").map(|chain| chain_map(&chain, 81)), Ok(78));
+        assert_eq!(almanac.chain("soil", "soil").unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_chain_errors() {
+        let mut almanac = example_almanac!();
+        assert_eq!(almanac.chain("location", "seed"), Err(ChainError::Broken { from: "location".to_string(), to: "seed".to_string() }));
+
+        almanac.categories.remove(3);
+        assert_eq!(almanac.chain("seed", "location"), Err(ChainError::Broken { from: "seed".to_string(), to: "location".to_string() }));
+
+        let mut almanac = example_almanac!();
+        almanac.categories.push(Category { source: "seed".to_string(), target: "water".to_string(), mappings: vec![] });
+        assert_eq!(almanac.chain("seed", "location"), Err(ChainError::Ambiguous { from: "seed".to_string(), to: "location".to_string() }));
+        // the detour does not matter before fertilizer
+        assert_eq!(almanac.chain("soil", "location").unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_chain_with_exponentially_many_paths() {
+        // every category maps to every later one, so the number of paths doubles with each category
+        let names: Vec<String> = (0..40).map(|i| format!("c{}", i)).collect();
+        let mut categories = vec![];
+        for i in 0..names.len() {
+            for j in i + 1..names.len() {
+                categories.push(Category { source: names[i].clone(), target: names[j].clone(), mappings: vec![] });
+            }
+        }
+        // the only way to "end" is the last map out of c0, after all the others were tried
+        categories.push(Category { source: names[0].clone(), target: "end".to_string(), mappings: vec![] });
+        let almanac = Almanac { seeds: vec![], categories };
+        assert_eq!(almanac.chain("c0", "c39"), Err(ChainError::Ambiguous { from: "c0".to_string(), to: "c39".to_string() }));
+        // the categories which cannot lead to the target are never entered
+        assert_eq!(almanac.chain("c0", "nowhere"), Err(ChainError::Broken { from: "c0".to_string(), to: "nowhere".to_string() }));
+        assert_eq!(almanac.chain("c0", "end").map(|chain| chain.len()), Ok(1));
+    }
 }
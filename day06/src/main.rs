@@ -1,43 +1,131 @@
-fn parse_input(input: &str) -> Vec<(u64, u64)> {
+use std::ops::RangeInclusive;
+
+type Race = (u128, u128);
+
+#[derive(Debug, PartialEq)]
+struct RaceAnalysis {
+    // press times beating the record, None if the record cannot be beaten
+    winning_presses: Option<RangeInclusive<u128>>,
+    // press time going the furthest, for odd durations the later one is equally good
+    best_press: u128,
+    // None if it does not fit in a u128
+    max_distance: Option<u128>,
+}
+
+enum ParseMode {
+    // every column is its own race
+    Races,
+    // the spaces are bad kerning, all digits of a line form one number
+    SingleRace,
+}
+
+fn parse_input(input: &str, mode: ParseMode) -> Vec<Race> {
     let mut lines = input.lines();
-    let extract_numbers = |line: &str| -> Vec<u64>{
-        line.split(":").skip(1).next().unwrap().split(" ").filter(|s| s.len() > 0).map(|x| x.parse::<u64>().unwrap()).collect()
+    let extract_numbers = |line: &str| -> Vec<u128>{
+        let numbers = line.split(':').nth(1).unwrap();
+        match mode {
+            ParseMode::Races => numbers.split(' ').filter(|s| !s.is_empty()).map(|x| x.parse::<u128>().unwrap()).collect(),
+            ParseMode::SingleRace => vec![numbers.replace(' ', "").parse::<u128>().unwrap()],
+        }
     };
-    let durations: Vec<u64> = extract_numbers(lines.next().unwrap());
-    let distances: Vec<u64> = extract_numbers(lines.next().unwrap());
+    let durations: Vec<u128> = extract_numbers(lines.next().unwrap());
+    let distances: Vec<u128> = extract_numbers(lines.next().unwrap());
     assert_eq!(durations.len(), distances.len());
-    (0..durations.len()).map(|i| (durations[i], distances[i])).collect()
+    durations.into_iter().zip(distances).collect()
+}
+
+// the boat travels t_press * (duration - t_press), which has to beat the record
+fn wins(race: &Race, t_press: u128) -> bool {
+    match t_press.checked_mul(race.0 - t_press) {
+        Some(distance) => distance > race.1,
+        // further than any u128 record
+        None => true,
+    }
+}
+
+// smallest press time beating the record, if there is one
+fn min_winning_press(race: &Race) -> Option<u128> {
+    let duration = race.0;
+    // the distance grows up to duration / 2, so binary search for the first win
+    // the roots of t² - duration*t + record would need more than 128 bits for large durations
+    let (mut low, mut high) = (0, duration / 2);
+    if !wins(race, high) {
+        return None;
+    }
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if wins(race, mid) {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    Some(low)
+}
+
+fn analyse_race(race: &Race) -> RaceAnalysis {
+    let duration = race.0;
+    // the distance is symmetric around duration / 2
+    let winning_presses = min_winning_press(race).map(|t_press_min| t_press_min..=duration - t_press_min);
+    let best_press = duration / 2;
+    RaceAnalysis { winning_presses, best_press, max_distance: best_press.checked_mul(duration - best_press) }
+}
+
+fn number_of_ways(race: &Race) -> u128{
+    match analyse_race(race).winning_presses {
+        Some(presses) => presses.end() - presses.start() + 1,
+        None => 0,
+    }
 }
 
-fn number_of_ways(race: &(u64, u64)) -> u64{
-    let p = - (race.0 as f64);
-    let q = race.1 as f64;
-    fn pq_formel(p: f64, q: f64) -> (f64, f64){
-        let a = -p/2.0;
-        let b = ((p/2.0).powi(2) - q).sqrt();
-        (a + b, a - b)
+// one line per race, every column as wide as its widest entry so any u128 lines up
+fn race_table(races: &[Race]) -> String {
+    let mut rows = vec![["race", "time", "record", "winning presses", "best", "max dist", "ways"].map(String::from)];
+    for (i, race) in races.iter().enumerate() {
+        let analysis = analyse_race(race);
+        let presses = match &analysis.winning_presses {
+            Some(presses) => format!("{}..={}", presses.start(), presses.end()),
+            None => "-".to_string(),
+        };
+        let max_distance = match analysis.max_distance {
+            Some(distance) => distance.to_string(),
+            None => "overflow".to_string(),
+        };
+        rows.push([(i + 1).to_string(), race.0.to_string(), race.1.to_string(), presses, analysis.best_press.to_string(), max_distance, number_of_ways(race).to_string()]);
     }
-    let (v_max, v_min) = pq_formel(p, q);
-    let (t_press_min, t_press_max) = ((v_min + 0.55).round() as u64, (v_max-0.55).round() as u64);
-    t_press_max - t_press_min + 1
+    let widths: Vec<usize> = (0..rows[0].len()).map(|c| rows.iter().map(|row| row[c].len()).max().unwrap()).collect();
+    rows.iter().map(|row| {
+        let cells: Vec<String> = row.iter().zip(&widths).map(|(cell, &width)| format!("{:>width$}", cell)).collect();
+        cells.join(" ") + "\n"
+    }).collect()
 }
 
-fn solve_part1(races: &Vec<(u64, u64)>) -> u64{
-    races.iter().map(|r| number_of_ways(r)).fold(1, |acc, x| acc*x)
+fn solve_part1(races: &[Race]) -> u128{
+    races.iter().map(number_of_ways).product()
+}
+
+fn solve_part2(race: &Race) -> u128{
+    number_of_ways(race)
 }
 
 fn main() {
     let input = include_str!("../input");
-    let races = parse_input(input);
+    let races = parse_input(input, ParseMode::Races);
+    let race = parse_input(input, ParseMode::SingleRace)[0];
 
+    // `table` prints how every race can be won instead of solving
+    if std::env::args().nth(1).as_deref() == Some("table") {
+        return print!("{}", race_table(&races));
+    }
     println!("Part 1: {}", solve_part1(&races));
+    println!("Part 2: {}", solve_part2(&race));
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const EXAMPLE: &'static str = 
+    const EXAMPLE: &str =
 "Time:      7  15   30
 Distance:  9  40  200";
 
@@ -50,17 +138,64 @@ Distance:  9  40  200";
     #[test]
     fn test_parsing() {
         let races = example_races!();
-        assert_eq!(parse_input(&EXAMPLE), races);
+        assert_eq!(parse_input(EXAMPLE, ParseMode::Races), races);
+        assert_eq!(parse_input(EXAMPLE, ParseMode::SingleRace), vec![(71530, 940200)]);
     }
 
     #[test]
     fn test_nways() {
         let races = example_races!();
-        let win_ways = vec![4, 8, 9];
+        let win_ways = [4, 8, 9];
         for (i, r) in races.iter().enumerate(){
-            assert_eq!(number_of_ways(&r), win_ways[i]);
+            assert_eq!(number_of_ways(r), win_ways[i]);
         }
-        
+    }
+
+    #[test]
+    fn test_nways_exact_roots() {
+        // 2*4 = 4*2 = 8 only ties the record, so just 3 is left
+        assert_eq!(number_of_ways(&(6, 8)), 1);
+        // 3*3 = 9 ties the record, there is no way to win
+        assert_eq!(number_of_ways(&(6, 9)), 0);
+        assert_eq!(number_of_ways(&(6, 10)), 0);
+        assert_eq!(number_of_ways(&(0, 0)), 0);
+        // roots at 10^18 and 3*10^18, far beyond f64 precision
+        let big = 1_000_000_000_000_000_000u128;
+        assert_eq!(number_of_ways(&(4 * big, 3 * big * big)), 2 * big - 1);
+    }
+
+    #[test]
+    fn test_nways_huge_durations() {
+        // only pressing 0 or 2^70 loses
+        assert_eq!(number_of_ways(&(1 << 70, 5)), (1 << 70) - 1);
+        let big = 1u128 << 64;
+        // 1 * (2^64 - 1) is short of the record, 2 * (2^64 - 2) beats it
+        assert_eq!(number_of_ways(&(big, big)), big - 3);
+        // 1 * (max - 1) just loses, 2 * (max - 2) goes beyond any u128 record
+        assert_eq!(number_of_ways(&(u128::MAX, u128::MAX)), u128::MAX - 3);
+        let analysis = analyse_race(&(u128::MAX, 0));
+        assert_eq!(analysis.winning_presses, Some(1..=u128::MAX - 1));
+        assert_eq!(analysis.max_distance, None);
+        assert_eq!(analyse_race(&(big, 0)).max_distance, Some(1 << 126));
+    }
+
+    #[test]
+    fn test_analyse_race() {
+        assert_eq!(analyse_race(&(7, 9)), RaceAnalysis { winning_presses: Some(2..=5), best_press: 3, max_distance: Some(12) });
+        assert_eq!(analyse_race(&(30, 200)), RaceAnalysis { winning_presses: Some(11..=19), best_press: 15, max_distance: Some(225) });
+        assert_eq!(analyse_race(&(6, 9)), RaceAnalysis { winning_presses: None, best_press: 3, max_distance: Some(9) });
+    }
+
+    #[test]
+    fn test_race_table() {
+        let table = race_table(&[(7, 9), (u128::MAX, 0)]);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        // columns line up however long the numbers get
+        assert!(lines.iter().all(|line| line.len() == lines[0].len()));
+        assert!(lines[1].ends_with(" 4"));
+        assert!(lines[2].ends_with(&(u128::MAX - 1).to_string()));
+        assert!(lines[2].contains(" overflow "));
     }
 
     #[test]
@@ -68,4 +203,10 @@ Distance:  9  40  200";
         let races = example_races!();
         assert_eq!(solve_part1(&races), 288);
     }
+
+    #[test]
+    fn test_solve_part2() {
+        let race = parse_input(EXAMPLE, ParseMode::SingleRace)[0];
+        assert_eq!(solve_part2(&race), 71503);
+    }
 }
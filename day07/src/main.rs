@@ -1,123 +1,288 @@
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[path = "../../common/rng.rs"]
+mod rng;
+use rng::Rng;
 
 fn parse_input(input: &str) -> Vec<(&str, u64)> {
     fn extract(line: &str) -> (&str, u64){
-        let mut it = line.split(" ").filter(|s| s.len() > 0);
+        let mut it = line.split(' ').filter(|s| !s.is_empty());
         let hand = it.next().unwrap();
         let bid = it.next().unwrap().parse::<u64>().unwrap();
         (hand, bid)
     }
-    input.lines().map(|line| extract(line)).collect()
-}
-
-fn card_strength(card: char) -> u64{
-    match card{
-        '2' => 1,
-        '3' => 2,
-        '4' => 3,
-        '5' => 4,
-        '6' => 5,
-        '7' => 6,
-        '8' => 7,
-        '9' => 8,
-        'T' => 9,
-        'J' => 10,
-        'Q' => 11,
-        'K' => 12,
-        'A' => 13,
-        _ => panic!("unknown card")
-    }
-}
-
-#[derive(PartialEq, PartialOrd, Debug)]
-enum HandType{
-    HighCard,
-    OnePair,
-    TwoPair,
-    ThreeOfAKind,
-    FullHouse,
-    FourOfAKind,
-    FiveOfAKind,
-}
-
-fn hand_type(hand: &str) -> HandType{
-    let mut hand_bytes = [0; 5];
-    hand_bytes.clone_from_slice(&hand.as_bytes());
-    hand_bytes.sort();
-    let hand = hand_bytes;
-    let mut groups = vec![1];
-    for (i, _) in hand.iter().enumerate().skip(1){
-        if hand[i] == hand[i-1]{
+    input.lines().map(extract).collect()
+}
+
+#[derive(Debug)]
+enum TieBreak {
+    // compare the first card, then the second, ...
+    InOrder,
+    // compare the strongest card of each hand, then the second strongest, ...
+    Sorted,
+}
+
+#[derive(Debug)]
+struct Rules {
+    // all cards from weakest to strongest
+    card_order: &'static str,
+    // cards which pretend to be whatever makes the hand type strongest
+    wildcards: &'static str,
+    tie_break: TieBreak,
+}
+
+const STANDARD_RULES: Rules = Rules {
+    card_order: "23456789TJQKA",
+    wildcards: "",
+    tie_break: TieBreak::InOrder,
+};
+
+const JOKER_RULES: Rules = Rules {
+    card_order: "J23456789TQKA",
+    wildcards: "J",
+    tie_break: TieBreak::InOrder,
+};
+
+// standard cards with ties broken by the strongest card rather than the first, as in poker
+const POKER_RULES: Rules = Rules {
+    card_order: "23456789TJQKA",
+    wildcards: "",
+    tie_break: TieBreak::Sorted,
+};
+
+fn rules_by_name(name: &str) -> Option<&'static Rules>{
+    match name {
+        "standard" => Some(&STANDARD_RULES),
+        "joker" => Some(&JOKER_RULES),
+        "poker" => Some(&POKER_RULES),
+        _ => None,
+    }
+}
+
+fn card_strength(card: char, rules: &Rules) -> u64{
+    rules.card_order.find(card).expect("unknown card") as u64 + 1
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Hash)]
+struct HandType{
+    // sizes of the groups of equal cards, largest first
+    // comparing these lexicographically ranks hands of equal size
+    groups: Vec<usize>,
+}
+
+impl fmt::Display for HandType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.groups.as_slice(){
+            [5] => write!(f, "Five of a kind"),
+            [4, 1] => write!(f, "Four of a kind"),
+            [3, 2] => write!(f, "Full house"),
+            [3, 1, 1] => write!(f, "Three of a kind"),
+            [2, 2, 1] => write!(f, "Two pair"),
+            [2, 1, 1, 1] => write!(f, "One pair"),
+            [1, 1, 1, 1, 1] => write!(f, "High card"),
+            groups => write!(f, "{}", groups.iter().map(|g| g.to_string()).collect::<Vec<_>>().join("+")),
+        }
+    }
+}
+
+fn hand_type(hand: &str, rules: &Rules) -> HandType{
+    assert!(!hand.is_empty(), "empty hand");
+    let mut hand: Vec<u8> = hand.bytes().collect();
+    let wilds = hand.iter().filter(|&&b| rules.wildcards.contains(b as char)).count();
+    hand.retain(|&b| !rules.wildcards.contains(b as char));
+    hand.sort();
+    let mut groups = vec![];
+    for (i, _) in hand.iter().enumerate(){
+        if i > 0 && hand[i] == hand[i-1]{
             *(groups.last_mut().unwrap()) += 1;
         }else{
             groups.push(1);
         }
     }
     groups.sort_by(|a, b| b.cmp(a));
-    match groups.len(){
-        1 => HandType::FiveOfAKind,
-        2 => if groups[0] == 3{
-            HandType::FullHouse
-        }else{
-            HandType::FourOfAKind
-        },
-        3 => {
-            if groups[0] == 3{
-                HandType::ThreeOfAKind
-            }else{
-                HandType::TwoPair
+    // wildcards always do best joining the largest group
+    match groups.first_mut() {
+        Some(largest) => *largest += wilds,
+        None => groups.push(wilds),
+    }
+    HandType{ groups }
+}
+
+#[derive(Debug, Clone)]
+struct Hand<'a>{
+    cards: &'a str,
+    bid: u64,
+    // hand type first, then the card strengths in tie break order
+    key: (HandType, Vec<u64>),
+}
+
+impl<'a> Hand<'a>{
+    fn new(cards: &'a str, bid: u64, rules: &Rules) -> Self{
+        let strengths: Vec<u64> = cards.chars().map(|c| card_strength(c, rules)).collect();
+        let strengths = match rules.tie_break {
+            TieBreak::InOrder => strengths,
+            TieBreak::Sorted => {
+                let mut strengths = strengths;
+                strengths.sort_by(|a, b| b.cmp(a));
+                strengths
             }
-        },
-        4 => HandType::OnePair,
-        5 => HandType::HighCard,
-        _ => panic!("invalid hand length")
-    }
-}
-
-fn cmp_by_strength(a: &(&str, u64), b: &(&str, u64)) -> Ordering{
-    let a_hand_type = hand_type(a.0);
-    let b_hand_type = hand_type(b.0);
-    if a_hand_type < b_hand_type{
-        Ordering::Less
-    }else if a_hand_type > b_hand_type{
-        Ordering::Greater
-    }else{
-        let a_hand = a.0.as_bytes();
-        let b_hand = b.0.as_bytes();
-        assert_eq!(a_hand.len(), b_hand.len());
-        for i in 0..a_hand.len(){
-            let a_card_strength = card_strength(a_hand[i] as char);
-            let b_card_strength = card_strength(b_hand[i] as char);
-            if a_card_strength < b_card_strength{
-                return Ordering::Less;
-            }else if b_card_strength < a_card_strength{
-                return Ordering::Greater;
-            }else{
-                continue;
+        };
+        Hand{ cards, bid, key: (hand_type(cards, rules), strengths) }
+    }
+}
+
+fn cmp_by_strength(a: &Hand, b: &Hand) -> Ordering{
+    assert_eq!(a.cards.len(), b.cards.len(), "hands of different size");
+    a.key.cmp(&b.key)
+}
+
+fn solve_part1(hands: &[(&str, u64)], rules: &Rules) -> u64{
+    let mut hands: Vec<Hand> = hands.iter().map(|&(cards, bid)| Hand::new(cards, bid, rules)).collect();
+    hands.sort_by(cmp_by_strength);
+    hands.iter().enumerate().fold(0, |acc, (i, hand)|acc + hand.bid*(i as u64+1))
+}
+
+#[derive(Debug)]
+struct Tournament {
+    // every card in the deck, duplicates included
+    deck: Vec<char>,
+    players: usize,
+    hand_size: usize,
+    rounds: usize,
+    seed: u64,
+}
+
+impl Tournament {
+    // `copies` of every card of `cards`, like the four suits of a real deck
+    fn with_deck(cards: &str, copies: usize, players: usize, hand_size: usize, rounds: usize, seed: u64) -> Self{
+        let deck = cards.chars().flat_map(|c| std::iter::repeat_n(c, copies)).collect();
+        Tournament { deck, players, hand_size, rounds, seed }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct TournamentReport {
+    rounds: usize,
+    hands: usize,
+    // rounds where the best hands were equally strong
+    ties: usize,
+    type_counts: BTreeMap<HandType, usize>,
+    // rounds won by a hand of each type
+    type_wins: BTreeMap<HandType, usize>,
+    wildcard_hands: usize,
+    wildcard_wins: usize,
+}
+
+impl TournamentReport {
+    fn frequency(&self, hand_type: &HandType) -> f64{
+        *self.type_counts.get(hand_type).unwrap_or(&0) as f64 / self.hands as f64
+    }
+
+    fn win_rate(&self, hand_type: &HandType) -> f64{
+        match self.type_counts.get(hand_type) {
+            Some(&count) => *self.type_wins.get(hand_type).unwrap_or(&0) as f64 / count as f64,
+            None => 0.0,
+        }
+    }
+
+    fn wildcard_win_rate(&self) -> f64{
+        if self.wildcard_hands == 0 {
+            return 0.0;
+        }
+        self.wildcard_wins as f64 / self.wildcard_hands as f64
+    }
+}
+
+// deals every player a hand each round and lets the strongest hand win
+// the same seed deals the same cards, so different rules can be compared round by round
+fn simulate(tournament: &Tournament, rules: &Rules) -> TournamentReport{
+    assert!(tournament.players * tournament.hand_size <= tournament.deck.len(), "deck too small for all players");
+    let mut rng = Rng(tournament.seed);
+    let mut deck = tournament.deck.clone();
+    let mut report = TournamentReport { rounds: tournament.rounds, ..Default::default() };
+    for _ in 0..tournament.rounds {
+        // partial Fisher-Yates, only the dealt cards need to be shuffled
+        let dealt = tournament.players * tournament.hand_size;
+        for i in 0..dealt {
+            let j = i + rng.below((deck.len() - i) as u128) as usize;
+            deck.swap(i, j);
+        }
+        let cards: Vec<String> = deck[..dealt].chunks(tournament.hand_size).map(|c| c.iter().collect()).collect();
+        let mut hands: Vec<Hand> = cards.iter().map(|c| Hand::new(c, 0, rules)).collect();
+        hands.sort_by(cmp_by_strength);
+
+        for hand in &hands {
+            *report.type_counts.entry(hand.key.0.clone()).or_default() += 1;
+            if hand.cards.chars().any(|c| rules.wildcards.contains(c)) {
+                report.wildcard_hands += 1;
             }
         }
-        Ordering::Equal
+        report.hands += hands.len();
+
+        let winner = hands.last().unwrap();
+        if hands.len() > 1 && cmp_by_strength(&hands[hands.len() - 2], winner) == Ordering::Equal {
+            report.ties += 1;
+            continue;
+        }
+        *report.type_wins.entry(winner.key.0.clone()).or_default() += 1;
+        if winner.cards.chars().any(|c| rules.wildcards.contains(c)) {
+            report.wildcard_wins += 1;
+        }
     }
+    report
 }
 
-fn solve_part1(hands: &Vec<(&str, u64)>) -> u64{
-    let mut hands = hands.clone();
-    hands.sort_by(cmp_by_strength); 
-    hands.iter().enumerate().fold(0, |acc, (i, (_, bid))|acc + bid*(i as u64+1))
+fn print_comparison(standard: &TournamentReport, joker: &TournamentReport) {
+    println!("{:>16} {:>10} {:>10} {:>10} {:>10}", "hand type", "freq", "freq J", "win", "win J");
+    let mut hand_types: Vec<&HandType> = standard.type_counts.keys().chain(joker.type_counts.keys()).collect();
+    hand_types.sort();
+    hand_types.dedup();
+    for hand_type in hand_types.into_iter().rev() {
+        println!("{:>16} {:>9.3}% {:>9.3}% {:>9.3}% {:>9.3}%", hand_type.to_string(),
+            100.0 * standard.frequency(hand_type), 100.0 * joker.frequency(hand_type),
+            100.0 * standard.win_rate(hand_type), 100.0 * joker.win_rate(hand_type));
+    }
+    println!("hands holding a joker win {:.3}% of their rounds", 100.0 * joker.wildcard_win_rate());
 }
 
 fn main() {
     let input = include_str!("../input");
-    let races = parse_input(input);
+    let hands = parse_input(input);
+
+    // `simulate [rounds] [seed]` plays tournaments with and without jokers instead of solving
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("simulate") {
+        let rounds = args.get(2).map_or(Some(20000), |a| a.parse::<usize>().ok());
+        let seed = args.get(3).map_or(Some(2023), |a| a.parse::<u64>().ok());
+        let (Some(rounds), Some(seed)) = (rounds, seed) else {
+            eprintln!("usage: day07 simulate [rounds] [seed]");
+            return;
+        };
+        let tournament = Tournament::with_deck(STANDARD_RULES.card_order, 4, 4, 5, rounds, seed);
+        print_comparison(&simulate(&tournament, &STANDARD_RULES), &simulate(&tournament, &JOKER_RULES));
+        return;
+    }
+    // `winnings <standard|joker|poker>` prints the total winnings under one rule set
+    if args.get(1).map(String::as_str) == Some("winnings") {
+        let Some(rules) = args.get(2).and_then(|name| rules_by_name(name)) else {
+            eprintln!("usage: day07 winnings <standard|joker|poker>");
+            return;
+        };
+        println!("{}", solve_part1(&hands, rules));
+        return;
+    }
 
-    println!("Part 1: {}", solve_part1(&races));
+    println!("Part 1: {}", solve_part1(&hands, &STANDARD_RULES));
+    println!("Part 2: {}", solve_part1(&hands, &JOKER_RULES));
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const EXAMPLE: &'static str = 
+    const EXAMPLE: &str =
 "32T3K 765
 T55J5 684
 KK677 28
@@ -134,22 +299,119 @@ QQQJA 483
     #[test]
     fn test_parsing() {
         let hands = example_hands!();
-        assert_eq!(parse_input(&EXAMPLE), hands);
+        assert_eq!(parse_input(EXAMPLE), hands);
     }
 
     #[test]
     fn test_hand_type() {
         let hands = example_hands!();
-        let hand_types = vec![HandType::OnePair, HandType::ThreeOfAKind, HandType::TwoPair, HandType::TwoPair, HandType::ThreeOfAKind];
+        let hand_types = ["One pair", "Three of a kind", "Two pair", "Two pair", "Three of a kind"];
+        for (i, (hand, _)) in hands.iter().enumerate(){
+            assert_eq!(hand_type(hand, &STANDARD_RULES).to_string(), hand_types[i]);
+        }
+        assert!(hand_type("KK677", &STANDARD_RULES) > hand_type("32T3K", &STANDARD_RULES));
+        assert!(hand_type("T55J5", &STANDARD_RULES) > hand_type("KTJJT", &STANDARD_RULES));
+    }
+
+    #[test]
+    fn test_hand_type_jokers() {
+        let hands = example_hands!();
+        let hand_types = ["One pair", "Four of a kind", "Two pair", "Four of a kind", "Four of a kind"];
         for (i, (hand, _)) in hands.iter().enumerate(){
-            assert_eq!(hand_type(&hand), hand_types[i]);
-        } 
-        
+            assert_eq!(hand_type(hand, &JOKER_RULES).to_string(), hand_types[i]);
+        }
+        assert_eq!(hand_type("JJJJJ", &JOKER_RULES).to_string(), "Five of a kind");
+        assert_eq!(hand_type("2JJ3J", &JOKER_RULES).to_string(), "Four of a kind");
+        assert_eq!(hand_type("2233J", &JOKER_RULES).to_string(), "Full house");
+    }
+
+    #[test]
+    fn test_hand_type_any_size() {
+        assert_eq!(hand_type("A", &STANDARD_RULES).groups, vec![1]);
+        assert_eq!(hand_type("KK", &STANDARD_RULES).groups, vec![2]);
+        assert_eq!(hand_type("223344", &STANDARD_RULES).to_string(), "2+2+2");
+        assert_eq!(hand_type("2233J4", &JOKER_RULES).to_string(), "3+2+1");
+        // two triples lose against four of a kind but beat a full house with a single
+        assert!(hand_type("222333", &STANDARD_RULES) < hand_type("222235", &STANDARD_RULES));
+        assert!(hand_type("222333", &STANDARD_RULES) > hand_type("222334", &STANDARD_RULES));
+    }
+
+    #[test]
+    fn test_tie_break() {
+        let cmp = |a, b, rules| cmp_by_strength(&Hand::new(a, 0, rules), &Hand::new(b, 0, rules));
+        // J is weaker than 2 with jokers
+        assert_eq!(cmp("JKKK2", "QQQQ2", &STANDARD_RULES), Ordering::Less);
+        assert_eq!(cmp("JKKK2", "QQQQ2", &JOKER_RULES), Ordering::Less);
+        assert_eq!(cmp("J2345", "22345", &JOKER_RULES), Ordering::Less);
+        assert_eq!(cmp("2345A", "K2345", &STANDARD_RULES), Ordering::Less);
+        assert_eq!(cmp("2345A", "K2345", &POKER_RULES), Ordering::Greater);
+        assert_eq!(cmp("32T3K", "33T2K", &POKER_RULES), Ordering::Equal);
+        assert_eq!(cmp("T9876543", "T9876542", &STANDARD_RULES), Ordering::Greater);
     }
 
     #[test]
     fn test_solve_part1() {
         let hands = example_hands!();
-        assert_eq!(solve_part1(&hands), 6440);
+        assert_eq!(solve_part1(&hands, &STANDARD_RULES), 6440);
+    }
+
+    #[test]
+    fn test_solve_part2() {
+        let hands = example_hands!();
+        assert_eq!(solve_part1(&hands, &JOKER_RULES), 5905);
+    }
+
+    #[test]
+    fn test_rules_by_name() {
+        let hands = example_hands!();
+        assert_eq!(solve_part1(&hands, rules_by_name("joker").unwrap()), 5905);
+        // the ace only counts first when sorted
+        let hands = vec![("2345A", 1), ("K2345", 10)];
+        assert_eq!(solve_part1(&hands, rules_by_name("standard").unwrap()), 1 + 2 * 10);
+        assert_eq!(solve_part1(&hands, rules_by_name("poker").unwrap()), 2 + 10);
+        assert!(rules_by_name("bridge").is_none());
+    }
+
+    #[test]
+    fn test_rng() {
+        let mut a = Rng(7);
+        let mut b = Rng(7);
+        for _ in 0..100 {
+            let x = a.below(13);
+            assert_eq!(x, b.below(13));
+            assert!(x < 13);
+        }
+    }
+
+    #[test]
+    fn test_simulate() {
+        let tournament = Tournament::with_deck(STANDARD_RULES.card_order, 4, 4, 5, 2000, 1);
+        let standard = simulate(&tournament, &STANDARD_RULES);
+        let joker = simulate(&tournament, &JOKER_RULES);
+        assert_eq!(standard, simulate(&tournament, &STANDARD_RULES));
+        assert_eq!(standard.hands, 8000);
+        assert_eq!(standard.type_counts.values().sum::<usize>(), 8000);
+        assert_eq!(standard.type_wins.values().sum::<usize>() + standard.ties, 2000);
+        // no wildcards without the joker rule
+        assert_eq!(standard.wildcard_hands, 0);
+        assert!(joker.wildcard_hands > 0);
+
+        // jokers only ever improve a hand, so high cards become rarer and strong hands more common
+        let high_card = hand_type("23456", &STANDARD_RULES);
+        let four_of_a_kind = hand_type("22223", &STANDARD_RULES);
+        assert!(joker.frequency(&high_card) < standard.frequency(&high_card));
+        assert!(joker.frequency(&four_of_a_kind) > standard.frequency(&four_of_a_kind));
+        assert!(joker.wildcard_win_rate() > 0.25);
+    }
+
+    #[test]
+    fn test_simulate_single_player() {
+        let tournament = Tournament::with_deck("AK", 5, 1, 5, 10, 3);
+        let report = simulate(&tournament, &STANDARD_RULES);
+        assert_eq!(report.type_wins.values().sum::<usize>(), 10);
+        // with nobody to lose against every hand wins
+        for hand_type in report.type_counts.keys() {
+            assert_eq!(report.win_rate(hand_type), 1.0);
+        }
     }
 }
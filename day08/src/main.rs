@@ -5,15 +5,29 @@ enum Instruction {
     Left,
     Right,
 }
-type Node<'a> = &'a str;
+// dense index into the node arrays of a `Map`
+type Node = usize;
 
 #[derive(PartialEq, Debug)]
 struct Map<'a> {
     instructions: Vec<Instruction>,
-    nodes: BTreeMap<Node<'a>, (Node<'a>, Node<'a>)>,
+    // node names are interned, `names[node]` is the name and `ids[name]` the node
+    names: Vec<&'a str>,
+    ids: BTreeMap<&'a str, Node>,
+    left: Vec<Node>,
+    right: Vec<Node>,
 }
 
-fn parse_input(input: &str) -> Map {
+impl<'a> Map<'a> {
+    fn id(&self, name: &str) -> Node {
+        *self
+            .ids
+            .get(name)
+            .unwrap_or_else(|| panic!("unknown node {}", name))
+    }
+}
+
+fn parse_input(input: &str) -> Map<'_> {
     let mut lines = input.lines();
     let first_line = lines.next().unwrap();
     let instructions = first_line
@@ -28,49 +42,349 @@ fn parse_input(input: &str) -> Map {
         .collect::<Vec<Instruction>>();
     lines.next(); // empty line
     fn extract(line: &str) -> (&str, (&str, &str)) {
-        let node = &line[0..3];
-        let left_node = &line[7..10];
-        let right_node = &line[12..15];
-        (node, (left_node, right_node))
+        // NAME = (LEFT, RIGHT)
+        let (node, children) = line.split_once('=').expect("missing \"=\"");
+        let children = children
+            .trim()
+            .strip_prefix('(')
+            .and_then(|c| c.strip_suffix(')'))
+            .expect("children not in parentheses");
+        let (left_node, right_node) = children.split_once(',').expect("missing \",\"");
+        (node.trim(), (left_node.trim(), right_node.trim()))
     }
-    let nodes = lines
-        .map(extract)
-        .collect::<BTreeMap<Node, (Node, Node)>>();
+    let definitions: Vec<(&str, (&str, &str))> =
+        lines.filter(|l| !l.is_empty()).map(extract).collect();
+
+    let names: Vec<&str> = definitions.iter().map(|(node, _)| *node).collect();
+    let ids: BTreeMap<&str, Node> = names.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+    assert_eq!(ids.len(), names.len(), "node defined twice");
+    let id = |name: &str| -> Node {
+        *ids.get(name)
+            .unwrap_or_else(|| panic!("node {} is never defined", name))
+    };
+    let left = definitions.iter().map(|(_, (l, _))| id(l)).collect();
+    let right = definitions.iter().map(|(_, (_, r))| id(r)).collect();
 
     Map {
         instructions,
-        nodes,
+        names,
+        ids,
+        left,
+        right,
+    }
+}
+
+fn step(map: &Map, node: Node, index: usize) -> Node {
+    match map.instructions[index] {
+        Instruction::Left => map.left[node],
+        Instruction::Right => map.right[node],
     }
 }
 
 fn solve_part1(map: &Map) -> usize {
-    let start = "AAA";
-    let goal = "ZZZ";
+    let start = map.id("AAA");
+    let goal = map.id("ZZZ");
     let mut steps = 0;
     let mut node = start;
-    while node != goal{
+    while node != goal {
         let index = steps % map.instructions.len();
-        node = match map.instructions[index]{
-            Instruction::Left => map.nodes[node].0,
-            Instruction::Right => map.nodes[node].1
-        };
+        node = step(map, node, index);
         steps += 1;
     }
     steps
 }
 
+// how a walker from one start behaves forever: after `tail` steps it repeats every `period` steps
+#[derive(PartialEq, Debug)]
+struct Cycle {
+    tail: usize,
+    period: usize,
+    // steps before the cycle starts on which the walker is on a goal node
+    tail_goals: Vec<usize>,
+    // steps in tail..tail + period on which the walker is on a goal node
+    cycle_goals: Vec<usize>,
+}
+
+#[derive(PartialEq, Debug)]
+enum NoSolution {
+    NoStarts,
+    // the walker from this start never reaches a goal node again after its tail
+    NeverAtGoal(String),
+    // the walkers reach their goals at steps which never line up
+    Incompatible,
+}
+
+fn analyse_cycle(map: &Map, start: Node, is_goal: impl Fn(Node) -> bool) -> Cycle {
+    // the walker's state is its node together with the next instruction to execute,
+    // `seen[state]` is the first step it was in that state
+    let mut seen = vec![None; map.names.len() * map.instructions.len()];
+    let mut goals = vec![];
+    let mut node = start;
+    let mut steps = 0;
+    loop {
+        let index = steps % map.instructions.len();
+        let state = node * map.instructions.len() + index;
+        if let Some(first) = seen[state] {
+            let (tail_goals, cycle_goals) = goals.iter().partition(|&&g| g < first);
+            return Cycle {
+                tail: first,
+                period: steps - first,
+                tail_goals,
+                cycle_goals,
+            };
+        }
+        seen[state] = Some(steps);
+        if is_goal(node) {
+            goals.push(steps);
+        }
+        node = step(map, node, index);
+        steps += 1;
+    }
+}
+
+fn ext_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = ext_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+// merges x ≡ a.0 (mod a.1) and x ≡ b.0 (mod b.1), the moduli need not be coprime
+fn crt(a: (u128, u128), b: (u128, u128)) -> Option<(u128, u128)> {
+    let (r1, m1) = (a.0 as i128, a.1 as i128);
+    let (r2, m2) = (b.0 as i128, b.1 as i128);
+    let (g, p, _) = ext_gcd(m1, m2);
+    if (r2 - r1) % g != 0 {
+        return None;
+    }
+    let lcm = m1 / g * m2;
+    // x = r1 + m1 * k with k ≡ (r2 - r1) / g * p (mod m2 / g)
+    let m2g = m2 / g;
+    let k = ((r2 - r1) / g % m2g * (p % m2g)).rem_euclid(m2g);
+    Some(((r1 + m1 * k).rem_euclid(lcm) as u128, lcm as u128))
+}
+
+fn solve_part2(map: &Map) -> Result<u128, NoSolution> {
+    let starts: Vec<Node> = (0..map.names.len())
+        .filter(|&n| map.names[n].ends_with('A'))
+        .collect();
+    if starts.is_empty() {
+        return Err(NoSolution::NoStarts);
+    }
+    let is_goal = |n: Node| map.names[n].ends_with('Z');
+    let cycles: Vec<Cycle> = starts
+        .iter()
+        .map(|&s| analyse_cycle(map, s, is_goal))
+        .collect();
+    for (start, cycle) in starts.iter().zip(&cycles) {
+        if cycle.cycle_goals.is_empty() && cycle.tail_goals.is_empty() {
+            return Err(NoSolution::NeverAtGoal(map.names[*start].to_string()));
+        }
+    }
+
+    // before every walker is in its cycle, just walk them all
+    let max_tail = cycles.iter().map(|c| c.tail).max().unwrap();
+    let mut nodes = starts.clone();
+    for steps in 0..max_tail {
+        if nodes.iter().all(|&n| is_goal(n)) {
+            return Ok(steps as u128);
+        }
+        let index = steps % map.instructions.len();
+        nodes = nodes.into_iter().map(|n| step(map, n, index)).collect();
+    }
+
+    // afterwards every walker is at a goal exactly on the steps congruent to one of its cycle goals
+    let mut combined = vec![(0, 1)];
+    for (start, cycle) in starts.iter().zip(&cycles) {
+        if cycle.cycle_goals.is_empty() {
+            return Err(NoSolution::NeverAtGoal(map.names[*start].to_string()));
+        }
+        let period = cycle.period as u128;
+        combined = combined
+            .iter()
+            .flat_map(|&c| {
+                cycle
+                    .cycle_goals
+                    .iter()
+                    .filter_map(move |&g| crt(c, (g as u128 % period, period)))
+            })
+            .collect();
+        combined.sort();
+        combined.dedup();
+    }
+    let max_tail = max_tail as u128;
+    combined
+        .into_iter()
+        .map(|(r, m)| {
+            // smallest step congruent to r which every walker reaches inside its cycle
+            if r >= max_tail {
+                r
+            } else {
+                r + (max_tail - r).div_ceil(m) * m
+            }
+        })
+        .min()
+        .ok_or(NoSolution::Incompatible)
+}
+
+// answers "where is a walker after n steps" in O(log n) without walking
+struct JumpTable {
+    period: usize,
+    // partial[i][node] is where a walker from node is after i < period steps
+    partial: Vec<Vec<Node>>,
+    // jumps[k][node] is where a walker from node is after 2^k full runs through the instructions
+    jumps: Vec<Vec<Node>>,
+}
+
+impl JumpTable {
+    fn new(map: &Map) -> Self {
+        let period = map.instructions.len();
+        let mut partial = vec![(0..map.names.len()).collect::<Vec<Node>>()];
+        for index in 0..period {
+            let next = partial[index]
+                .iter()
+                .map(|&n| step(map, n, index))
+                .collect();
+            partial.push(next);
+        }
+        let mut jumps = vec![partial.pop().unwrap()];
+        for k in 1..u64::BITS as usize {
+            let previous = &jumps[k - 1];
+            let next = previous.iter().map(|&n| previous[n]).collect();
+            jumps.push(next);
+        }
+        JumpTable {
+            period,
+            partial,
+            jumps,
+        }
+    }
+
+    // position after `steps` steps, starting with the first instruction
+    fn position(&self, node: Node, steps: u64) -> Node {
+        let runs = steps / self.period as u64;
+        let rest = (steps % self.period as u64) as usize;
+        let mut node = node;
+        for (k, jump) in self.jumps.iter().enumerate() {
+            if runs >> k & 1 == 1 {
+                node = jump[node];
+            }
+        }
+        self.partial[rest][node]
+    }
+}
+
+fn reachable(map: &Map, start: Node) -> Vec<bool> {
+    let mut visited = vec![false; map.names.len()];
+    let mut stack = vec![start];
+    while let Some(node) = stack.pop() {
+        if visited[node] {
+            continue;
+        }
+        visited[node] = true;
+        stack.push(map.left[node]);
+        stack.push(map.right[node]);
+    }
+    visited
+}
+
+// Graphviz graph of the network, start nodes are green and goal nodes red
+// with `clusters` every node only reachable from a single start is boxed together with it
+fn to_dot(map: &Map, clusters: bool) -> String {
+    let quote = |node: Node| format!("\"{}\"", map.names[node].replace('"', "\\\""));
+    let style = |node: Node| {
+        let name = map.names[node];
+        if name.ends_with('A') {
+            " [style=filled, fillcolor=palegreen]"
+        } else if name.ends_with('Z') {
+            " [style=filled, fillcolor=salmon]"
+        } else {
+            ""
+        }
+    };
+
+    // owner[node] is the only start reaching the node, if there is exactly one
+    let mut owner: Vec<Option<Node>> = vec![None; map.names.len()];
+    if clusters {
+        let mut reached_by = vec![0; map.names.len()];
+        for start in (0..map.names.len()).filter(|&n| map.names[n].ends_with('A')) {
+            for (node, _) in reachable(map, start).iter().enumerate().filter(|(_, &r)| r) {
+                reached_by[node] += 1;
+                owner[node] = Some(start);
+            }
+        }
+        for (node, &count) in reached_by.iter().enumerate() {
+            if count > 1 {
+                owner[node] = None;
+            }
+        }
+    }
+
+    let mut dot = String::from("digraph network {\n");
+    let mut starts: Vec<Node> = owner.iter().flatten().copied().collect();
+    starts.sort();
+    starts.dedup();
+    for start in starts {
+        dot += &format!("    subgraph \"cluster_{}\" {{\n", start);
+        dot += &format!("        label={};\n", quote(start));
+        for node in (0..map.names.len()).filter(|&n| owner[n] == Some(start)) {
+            dot += &format!("        {}{};\n", quote(node), style(node));
+        }
+        dot += "    }\n";
+    }
+    for node in (0..map.names.len()).filter(|&n| owner[n].is_none()) {
+        dot += &format!("    {}{};\n", quote(node), style(node));
+    }
+    for node in 0..map.names.len() {
+        let (left, right) = (map.left[node], map.right[node]);
+        if left == right {
+            dot += &format!("    {} -> {} [label=\"L/R\"];\n", quote(node), quote(left));
+        } else {
+            dot += &format!("    {} -> {} [label=\"L\"];\n", quote(node), quote(left));
+            dot += &format!("    {} -> {} [label=\"R\"];\n", quote(node), quote(right));
+        }
+    }
+    dot += "}\n";
+    dot
+}
+
 fn main() {
     let input = include_str!("../input");
     let map = parse_input(input);
 
+    // `dot` or `dot-clusters` prints the network for Graphviz instead of solving
+    // `position <node> <steps>` prints where a walker starting at the node ends up
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("dot") => return print!("{}", to_dot(&map, false)),
+        Some("dot-clusters") => return print!("{}", to_dot(&map, true)),
+        Some("position") => {
+            let start = args.get(2).and_then(|name| map.ids.get(name.as_str()));
+            let steps = args.get(3).and_then(|steps| steps.parse::<u64>().ok());
+            let (Some(&start), Some(steps)) = (start, steps) else {
+                eprintln!("usage: day08 position <node> <steps>");
+                return;
+            };
+            let node = JumpTable::new(&map).position(start, steps);
+            return println!("{}", map.names[node]);
+        }
+        _ => {}
+    }
+
     println!("Part 1: {}", solve_part1(&map));
+    match solve_part2(&map) {
+        Ok(steps) => println!("Part 2: {}", steps),
+        Err(e) => println!("Part 2: no solution, {:?}", e),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const EXAMPLE1: &'static str = "RL
+    const EXAMPLE1: &str = "RL
 
 AAA = (BBB, CCC)
 BBB = (DDD, EEE)
@@ -81,7 +395,7 @@ GGG = (GGG, GGG)
 ZZZ = (ZZZ, ZZZ)
 ";
 
-    const EXAMPLE2: &'static str = "LLR
+    const EXAMPLE2: &str = "LLR
 
 AAA = (BBB, BBB)
 BBB = (AAA, ZZZ)
@@ -89,45 +403,68 @@ ZZZ = (ZZZ, ZZZ)
 ";
 
     macro_rules! example_1_map {
-        () => {
+        () => {{
+            let names = vec!["AAA", "BBB", "CCC", "DDD", "EEE", "GGG", "ZZZ"];
             Map {
                 instructions: vec![Instruction::Right, Instruction::Left],
-                nodes: BTreeMap::from([
-                    ("AAA", ("BBB", "CCC")),
-                    ("BBB", ("DDD", "EEE")),
-                    ("CCC", ("ZZZ", "GGG")),
-                    ("DDD", ("DDD", "DDD")),
-                    ("EEE", ("EEE", "EEE")),
-                    ("GGG", ("GGG", "GGG")),
-                    ("ZZZ", ("ZZZ", "ZZZ")),
-                ]),
+                ids: names.iter().enumerate().map(|(i, &n)| (n, i)).collect(),
+                names,
+                left: vec![1, 3, 6, 3, 4, 5, 6],
+                right: vec![2, 4, 5, 3, 4, 5, 6],
             }
-        };
+        }};
     }
 
     macro_rules! example_2_map {
-        () => {
+        () => {{
+            let names = vec!["AAA", "BBB", "ZZZ"];
             Map {
                 instructions: vec![Instruction::Left, Instruction::Left, Instruction::Right],
-                nodes: BTreeMap::from([
-                    ("AAA", ("BBB", "BBB")),
-                    ("BBB", ("AAA", "ZZZ")),
-                    ("ZZZ", ("ZZZ", "ZZZ")),
-                ]),
+                ids: names.iter().enumerate().map(|(i, &n)| (n, i)).collect(),
+                names,
+                left: vec![1, 0, 2],
+                right: vec![1, 2, 2],
             }
-        };
+        }};
     }
 
     #[test]
     fn test_parsing1() {
         let map = example_1_map!();
-        assert_eq!(parse_input(&EXAMPLE1), map);
+        assert_eq!(parse_input(EXAMPLE1), map);
     }
 
     #[test]
     fn test_parsing2() {
         let map = example_2_map!();
-        assert_eq!(parse_input(&EXAMPLE2), map);
+        assert_eq!(parse_input(EXAMPLE2), map);
+    }
+
+    #[test]
+    fn test_parsing_long_names() {
+        let map = parse_input(
+            "LR
+
+START = (middle, START)
+middle = (GOAL_NODE, GOAL_NODE)
+GOAL_NODE = (GOAL_NODE, GOAL_NODE)
+",
+        );
+        assert_eq!(map.names, vec!["START", "middle", "GOAL_NODE"]);
+        assert_eq!(map.id("GOAL_NODE"), 2);
+        assert_eq!(map.left, vec![1, 2, 2]);
+        assert_eq!(map.right, vec![0, 2, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "node YYY is never defined")]
+    fn test_parsing_undefined_node() {
+        parse_input(
+            "L
+
+XXX = (XXX, YYY)
+",
+        );
     }
 
     #[test]
@@ -138,4 +475,163 @@ ZZZ = (ZZZ, ZZZ)
         let steps = example_2_map!();
         assert_eq!(solve_part1(&steps), 6);
     }
+
+    const EXAMPLE3: &str = "LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)
+";
+
+    #[test]
+    fn test_analyse_cycle() {
+        let map = parse_input(EXAMPLE3);
+        let is_goal = |n: Node| map.names[n].ends_with('Z');
+        assert_eq!(
+            analyse_cycle(&map, map.id("11A"), is_goal),
+            Cycle {
+                tail: 1,
+                period: 2,
+                tail_goals: vec![],
+                cycle_goals: vec![2],
+            }
+        );
+        assert_eq!(
+            analyse_cycle(&map, map.id("22A"), is_goal),
+            Cycle {
+                tail: 1,
+                period: 6,
+                tail_goals: vec![],
+                cycle_goals: vec![3, 6],
+            }
+        );
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt((2, 3), (3, 5)), Some((8, 15)));
+        assert_eq!(crt((1, 4), (3, 6)), Some((9, 12)));
+        assert_eq!(crt((0, 4), (1, 6)), None);
+    }
+
+    #[test]
+    fn test_solve_part2() {
+        let map = parse_input(EXAMPLE3);
+        assert_eq!(solve_part2(&map), Ok(6));
+    }
+
+    #[test]
+    fn test_solve_part2_tails() {
+        // 11A is only at a goal once, before its cycle starts
+        let map = parse_input(
+            "L
+
+11A = (11Z, 11Z)
+11Z = (11B, 11B)
+11B = (11B, 11B)
+22A = (22Z, 22Z)
+22Z = (22Z, 22Z)
+",
+        );
+        assert_eq!(solve_part2(&map), Ok(1));
+        // offset cycles: 11A is at its goal on odd steps, 22A on even ones
+        let map = parse_input(
+            "L
+
+11A = (11Z, 11Z)
+11Z = (11A, 11A)
+22A = (22B, 22B)
+22B = (22Z, 22Z)
+22Z = (22B, 22B)
+",
+        );
+        assert_eq!(solve_part2(&map), Err(NoSolution::Incompatible));
+    }
+
+    #[test]
+    fn test_solve_part2_no_solution() {
+        let map = parse_input(
+            "L
+
+11A = (11Z, 11Z)
+11Z = (11Z, 11Z)
+22A = (22B, 22B)
+22B = (22B, 22B)
+",
+        );
+        assert_eq!(
+            solve_part2(&map),
+            Err(NoSolution::NeverAtGoal("22A".to_string()))
+        );
+        let input = EXAMPLE3.replace('A', "Q");
+        let map = parse_input(&input);
+        assert_eq!(solve_part2(&map), Err(NoSolution::NoStarts));
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let map = example_2_map!();
+        assert_eq!(
+            to_dot(&map, false),
+            "digraph network {
+    \"AAA\" [style=filled, fillcolor=palegreen];
+    \"BBB\";
+    \"ZZZ\" [style=filled, fillcolor=salmon];
+    \"AAA\" -> \"BBB\" [label=\"L/R\"];
+    \"BBB\" -> \"AAA\" [label=\"L\"];
+    \"BBB\" -> \"ZZZ\" [label=\"R\"];
+    \"ZZZ\" -> \"ZZZ\" [label=\"L/R\"];
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_to_dot_clusters() {
+        let mut input = EXAMPLE3.to_string();
+        input += "33A = (33Z, 33Z)\n33Z = (33A, XXX)\n";
+        let map = parse_input(&input);
+        let dot = to_dot(&map, true);
+        // XXX is reachable by every ghost, so it stays outside the clusters
+        let cluster_11 = dot.find("subgraph \"cluster_0\"").unwrap();
+        let cluster_22 = dot.find("subgraph \"cluster_3\"").unwrap();
+        let cluster_33 = dot.find("subgraph \"cluster_8\"").unwrap();
+        assert!(cluster_11 < dot.find("\"11Z\" [").unwrap());
+        assert!(dot.find("\"11Z\" [").unwrap() < cluster_22);
+        assert!(cluster_22 < dot.find("\"22Z\" [").unwrap());
+        assert!(dot.find("\"22Z\" [").unwrap() < cluster_33);
+        assert!(cluster_33 < dot.find("\"33Z\" [").unwrap());
+        assert!(dot.rfind("    }\n").unwrap() < dot.find("    \"XXX\";").unwrap());
+        // nodes with equal children get a single L/R edge
+        assert_eq!(dot.matches(" -> ").count(), 2 * 10 - 5);
+    }
+
+    #[test]
+    fn test_jump_table() {
+        let map = parse_input(EXAMPLE3);
+        let jumps = JumpTable::new(&map);
+        for start in 0..map.names.len() {
+            let mut node = start;
+            for steps in 0..50 {
+                assert_eq!(jumps.position(start, steps), node);
+                node = step(&map, node, steps as usize % map.instructions.len());
+            }
+        }
+        // from 22A the walker is on 22Z on every third step, and 2^64 - 1 is a multiple of 3
+        let start = map.id("22A");
+        assert_eq!(
+            map.names[jumps.position(start, 3_000_000_000_000_003)],
+            "22Z"
+        );
+        assert_eq!(
+            map.names[jumps.position(start, 3_000_000_000_000_004)],
+            "22B"
+        );
+        assert_eq!(map.names[jumps.position(start, u64::MAX)], "22Z");
+    }
 }
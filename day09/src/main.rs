@@ -1,4 +1,5 @@
 use itertools::Itertools; // 0.10.0
+use num_bigint::BigInt;
 
 type SensorValue = i64;
 type History = Vec<SensorValue>;
@@ -20,58 +21,87 @@ fn parse_input(input: &str) -> PuzzleInput {
     PuzzleInput { histories }
 }
 
-fn sequences(history: &[SensorValue]) -> Vec<Vec<SensorValue>> {
-    fn differences(sequence: &[SensorValue]) -> Vec<SensorValue> {
-        sequence
+// a history whose differences run out of values before they are all zero
+#[derive(PartialEq, Debug)]
+struct NonPolynomial {
+    // how often the history was differenced before the values ran out
+    depth: usize,
+    // the last differences, which are still not all zero
+    residual: Vec<BigInt>,
+}
+
+// Newton forward differences: the first value of the history and of every difference sequence
+// a history is a polynomial only if differencing reaches a non-empty row of zeros, so a single
+// value or an empty history is rejected: it does not show which polynomial it belongs to
+fn forward_differences(history: &[SensorValue]) -> Result<Vec<BigInt>, NonPolynomial> {
+    let mut sequence: Vec<BigInt> = history.iter().map(|&v| BigInt::from(v)).collect();
+    let mut firsts = vec![];
+    loop {
+        if !sequence.is_empty() && sequence.iter().all(|v| *v == BigInt::ZERO) {
+            return Ok(firsts);
+        }
+        let next: Vec<BigInt> = sequence
             .iter()
             .tuple_windows()
             .map(|(prev, next)| next - prev)
-            .collect()
-    }
-    let mut sequences = vec![history.to_owned()];
-    let all_zero = |seq: &Vec<SensorValue>| -> bool {
-        for val in seq {
-            if *val != 0 {
-                return false;
-            }
+            .collect();
+        if next.is_empty() {
+            return Err(NonPolynomial {
+                depth: firsts.len(),
+                residual: sequence,
+            });
         }
-        true
-    };
-    while !all_zero(&sequences[sequences.len() - 1]) {
-        let last_sequence = &sequences[sequences.len() - 1];
-        sequences.push(differences(last_sequence));
+        firsts.push(sequence[0].clone());
+        sequence = next;
     }
-    let last_index = sequences.len() - 1;
-    sequences[last_index].push(0);
-    sequences[last_index].push(0);
-    for i in (0..sequences.len() - 1).rev() {
-        let delta = sequences[i + 1][0];
-        let current_value = sequences[i][0];
-        let extrapolated_backwards = current_value - delta;
-        sequences[i].insert(0, extrapolated_backwards);
-    }
-    for i in (0..sequences.len() - 1).rev() {
-        let delta = sequences[i + 1].last().unwrap();
-        let current_value = sequences[i].last().unwrap();
-        let extrapolated = current_value + delta;
-        sequences[i].push(extrapolated);
+}
+
+// value at index `x` of the history, where 0 is the first value and negative indices lie before it
+// value(x) = sum over j of Δ^j(first) * binomial(x, j), which also holds for negative x
+fn extrapolate(history: &[SensorValue], x: &BigInt) -> Result<BigInt, NonPolynomial> {
+    let mut binomial = BigInt::from(1);
+    let mut value = BigInt::ZERO;
+    for (j, difference) in forward_differences(history)?.iter().enumerate() {
+        value += difference * &binomial;
+        // binomial(x, j + 1) = binomial(x, j) * (x - j) / (j + 1), the division is always exact
+        binomial = binomial * (x - j) / (j + 1);
     }
-    sequences
+    Ok(value)
+}
+
+fn predict_forward(history: &[SensorValue], steps: i64) -> Result<BigInt, NonPolynomial> {
+    // the index is a big integer too, so any number of steps fits
+    extrapolate(history, &(BigInt::from(history.len()) - 1 + steps))
+}
+
+fn predict_backward(history: &[SensorValue], steps: i64) -> Result<BigInt, NonPolynomial> {
+    extrapolate(history, &-BigInt::from(steps))
 }
 
-fn solve_part1(input: &PuzzleInput) -> SensorValue {
+// the histories which are no polynomial, with their line in the input starting at 1
+fn validate(input: &PuzzleInput) -> Vec<(usize, NonPolynomial)> {
     input
         .histories
         .iter()
-        .map(|hist| *sequences(hist)[0].last().unwrap())
+        .enumerate()
+        .filter_map(|(i, history)| forward_differences(history).err().map(|e| (i + 1, e)))
+        .collect()
+}
+
+// histories which do not reduce to zero are left out of the sums
+fn solve_part1(input: &PuzzleInput) -> BigInt {
+    input
+        .histories
+        .iter()
+        .filter_map(|hist| predict_forward(hist, 1).ok())
         .sum()
 }
 
-fn solve_part2(input: &PuzzleInput) -> SensorValue {
+fn solve_part2(input: &PuzzleInput) -> BigInt {
     input
         .histories
         .iter()
-        .map(|hist| sequences(hist)[0][0])
+        .filter_map(|hist| predict_backward(hist, 1).ok())
         .sum()
 }
 
@@ -79,6 +109,12 @@ fn main() {
     let input = include_str!("../input");
     let input = parse_input(input);
 
+    for (line, rejected) in validate(&input) {
+        println!(
+            "Line {} never reduces to zero, after {} differences {:?} are left",
+            line, rejected.depth, rejected.residual
+        );
+    }
     println!("Part 1: {}", solve_part1(&input));
     println!("Part 2: {}", solve_part2(&input));
 }
@@ -87,7 +123,7 @@ fn main() {
 mod tests {
     use super::*;
 
-    const EXAMPLE: &'static str = "0 3 6 9 12 15
+    const EXAMPLE: &str = "0 3 6 9 12 15
 1 3 6 10 15 21
 10 13 16 21 30 45
 ";
@@ -104,10 +140,50 @@ mod tests {
         };
     }
 
+    // the full difference pyramid, extended by one value on both ends, to cross-check `extrapolate`
+    fn sequences(history: &[SensorValue]) -> Vec<Vec<SensorValue>> {
+        fn differences(sequence: &[SensorValue]) -> Vec<SensorValue> {
+            sequence
+                .iter()
+                .tuple_windows()
+                .map(|(prev, next)| next - prev)
+                .collect()
+        }
+        let mut sequences = vec![history.to_owned()];
+        let all_zero = |seq: &Vec<SensorValue>| -> bool {
+            for val in seq {
+                if *val != 0 {
+                    return false;
+                }
+            }
+            true
+        };
+        while !all_zero(&sequences[sequences.len() - 1]) {
+            let last_sequence = &sequences[sequences.len() - 1];
+            sequences.push(differences(last_sequence));
+        }
+        let last_index = sequences.len() - 1;
+        sequences[last_index].push(0);
+        sequences[last_index].push(0);
+        for i in (0..sequences.len() - 1).rev() {
+            let delta = sequences[i + 1][0];
+            let current_value = sequences[i][0];
+            let extrapolated_backwards = current_value - delta;
+            sequences[i].insert(0, extrapolated_backwards);
+        }
+        for i in (0..sequences.len() - 1).rev() {
+            let delta = sequences[i + 1].last().unwrap();
+            let current_value = sequences[i].last().unwrap();
+            let extrapolated = current_value + delta;
+            sequences[i].push(extrapolated);
+        }
+        sequences
+    }
+
     #[test]
     fn test_parsing() {
         let input = example_parsed!();
-        assert_eq!(parse_input(&EXAMPLE), input);
+        assert_eq!(parse_input(EXAMPLE), input);
     }
 
     #[test]
@@ -145,6 +221,95 @@ mod tests {
     #[test]
     fn test_solve_part1() {
         let input = example_parsed!();
-        assert_eq!(solve_part1(&input), 114);
+        assert_eq!(solve_part1(&input), BigInt::from(114));
+    }
+
+    #[test]
+    fn test_solve_part2() {
+        let input = example_parsed!();
+        assert_eq!(solve_part2(&input), BigInt::from(2));
+    }
+
+    #[test]
+    fn test_extrapolate_matches_sequences() {
+        let input = example_parsed!();
+        for history in &input.histories {
+            let pyramid = &sequences(history)[0];
+            for (i, value) in pyramid.iter().enumerate() {
+                assert_eq!(extrapolate(history, &BigInt::from(i as i64 - 1)), Ok(BigInt::from(*value)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let input = parse_input("0 3 6 9 12 15\n1 2 4 8 16\n1 3 6 10 15 21\n5\n0 0\n");
+        assert_eq!(
+            validate(&input),
+            vec![
+                (
+                    2,
+                    NonPolynomial {
+                        depth: 4,
+                        residual: vec![BigInt::from(1)],
+                    }
+                ),
+                (
+                    4,
+                    NonPolynomial {
+                        depth: 0,
+                        residual: vec![BigInt::from(5)],
+                    }
+                ),
+            ]
+        );
+        // extrapolation follows the same rule instead of making up a value
+        assert_eq!(
+            predict_forward(&input.histories[1], 1),
+            Err(NonPolynomial {
+                depth: 4,
+                residual: vec![BigInt::from(1)],
+            })
+        );
+        assert!(predict_backward(&input.histories[3], 1).is_err());
+        assert_eq!(predict_forward(&input.histories[4], 1), Ok(BigInt::ZERO));
+        // the powers of two are skipped instead of panicking
+        assert_eq!(solve_part1(&input), BigInt::from(18 + 28));
+        assert_eq!(solve_part2(&input), BigInt::from(-3));
+    }
+
+    #[test]
+    fn test_predict_far() {
+        let input = example_parsed!();
+        // 0 3 6 ... is 3x, 1 3 6 10 ... is (x + 1)(x + 2) / 2
+        assert_eq!(predict_forward(&input.histories[0], 5), Ok(BigInt::from(30)));
+        assert_eq!(predict_backward(&input.histories[0], 4), Ok(BigInt::from(-12)));
+        assert_eq!(predict_backward(&input.histories[1], 2), Ok(BigInt::from(0)));
+        assert_eq!(predict_backward(&input.histories[1], 3), Ok(BigInt::from(1)));
+        let x = BigInt::from(i64::MAX);
+        let expected = (&x + 1) * (&x + 2) / 2;
+        assert_eq!(extrapolate(&input.histories[1], &x), Ok(expected));
+        // the index lies past i64 at both ends
+        let max = BigInt::from(i64::MAX);
+        assert_eq!(predict_forward(&[1, 2, 3], i64::MAX), Ok(&max + 3));
+        let min = BigInt::from(i64::MIN);
+        assert_eq!(predict_backward(&[1, 2, 3], i64::MIN), Ok(1 - &min));
+        assert_eq!(predict_backward(&input.histories[0], i64::MIN), Ok(&min * -3));
+        // a constant needs two values to show it is one
+        assert_eq!(predict_forward(&[7, 7], i64::MAX), Ok(BigInt::from(7)));
+        assert_eq!(
+            predict_forward(&[7], i64::MAX),
+            Err(NonPolynomial {
+                depth: 0,
+                residual: vec![BigInt::from(7)],
+            })
+        );
+        assert_eq!(
+            predict_forward(&[], 3),
+            Err(NonPolynomial {
+                depth: 0,
+                residual: vec![],
+            })
+        );
     }
 }
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::fmt;
 
 #[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 enum Tile{
@@ -12,18 +12,16 @@ enum Tile{
     Start,
 }
 
-const WEST_CONN: [Tile; 3] = [SEPipe, NEPipe, HPipe];
-const NORTH_CONN: [Tile; 3] = [SEPipe, SWPipe, VPipe];
-const SOUTH_CONN: [Tile; 3] = [NEPipe, NWPipe, VPipe];
-const EAST_CONN: [Tile; 3] = [SWPipe, NWPipe, HPipe];
+const PIPES: [Tile; 6] = [VPipe, HPipe, NEPipe, NWPipe, SWPipe, SEPipe];
+const DIRECTIONS: [(i64, i64); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
 
 impl Tile {
     fn next(self, from: (i64, i64)) -> Option<(i64, i64)> {
         let next_dir = |a: (i64, i64), b: (i64, i64)| -> Option<(i64, i64)>{
             if from == a{
-                return Some(b)
+                Some(b)
             }else if from == b{
-                return Some(a)
+                Some(a)
             }else{
                 None
             }
@@ -42,13 +40,6 @@ impl Tile {
 
 use Tile::*;
 
-struct Connections{
-    north: Option<[Tile; 3]>,
-    south: Option<[Tile; 3]>,
-    west: Option<[Tile; 3]>,
-    east: Option<[Tile; 3]>,
-}
-
 #[derive(PartialEq, Debug)]
 struct PuzzleInput {
     width: usize,
@@ -59,13 +50,13 @@ struct PuzzleInput {
 type Coordinate = (i64, i64);
 
 impl PuzzleInput{
-    fn get_by_xy<'a>(&'a self, coords: Coordinate) -> Option<&'a Tile>{
+    fn get_by_xy(&self, coords: Coordinate) -> Option<&Tile>{
         let i = self.index(coords)?;
         Some(&self.tiles[i])
     }
 
     fn xy(&self, i: usize) -> Coordinate{
-        return ((i % self.width) as i64, (i / self.width) as i64)
+        ((i % self.width) as i64, (i / self.width) as i64)
     }
 
     fn index(&self, (x, y): Coordinate) -> Option<usize>{
@@ -75,13 +66,180 @@ impl PuzzleInput{
         if y < 0 || self.height as i64 - 1 < y{
             return None
         }
-        return Some(y as usize * self.width + x as usize)
+        Some(y as usize * self.width + x as usize)
+    }
+
+    fn iter_from(&self, xy: Coordinate, from: (i64, i64)) -> PipeSegment<'_>{
+        PipeSegment{
+            tilemap: self, curr: (xy, *self.get_by_xy(xy).unwrap()), from
+        }
+    }
+
+    fn start(&self) -> Result<Coordinate, LoopError>{
+        let start_index = self.tiles.iter().position(|&t| t == Tile::Start).ok_or(LoopError::NoStart)?;
+        Ok(self.xy(start_index))
+    }
+
+    // the pipe hidden below the start, deduced from the two neighbours connecting back to it
+    fn start_tile(&self) -> Result<Tile, LoopError>{
+        let start_xy = self.start()?;
+        let connected: Vec<(i64, i64)> = DIRECTIONS.into_iter().filter(|dir| {
+            let xy = (start_xy.0 + dir.0, start_xy.1 + dir.1);
+            let from = (-dir.0, -dir.1);
+            self.get_by_xy(xy).is_some_and(|tile| tile.next(from).is_some())
+        }).collect();
+        let connections = connected.iter().map(|dir| (start_xy.0 + dir.0, start_xy.1 + dir.1)).collect();
+        if connected.len() < 2{
+            return Err(LoopError::StartNotConnected{ start: start_xy, connections })
+        }
+        if connected.len() > 2{
+            return Err(LoopError::AmbiguousStart{ start: start_xy, connections })
+        }
+        Ok(PIPES.into_iter().find(|pipe| pipe.next(connected[0]) == Some(connected[1])).unwrap())
+    }
+
+    // coordinates of the loop through the start, beginning right after it and ending on it
+    fn find_loop(&self) -> Vec<Coordinate>{
+        self.trace_loop().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn trace_loop(&self) -> Result<Vec<Coordinate>, LoopError>{
+        let start_xy = self.start()?;
+        let start_tile = self.start_tile()?;
+        // leave the start through the first of its two openings
+        let dir = DIRECTIONS.into_iter().find(|&dir| start_tile.next(dir).is_some()).unwrap();
+        let begin = (start_xy.0 + dir.0, start_xy.1 + dir.1);
+        let mut coords = vec![begin];
+        let mut segment = self.iter_from(begin, (-dir.0, -dir.1));
+        coords.extend(segment.by_ref().map(|(xy, _)| xy));
+
+        let (last, last_tile) = segment.curr;
+        if last_tile == Start{
+            return Ok(coords)
+        }
+        // the segment stops either on a tile which does not connect back, or on a pipe leading off the map
+        match last_tile.next(segment.from){
+            None => Err(LoopError::DeadEnd{ at: coords[coords.len() - 2], towards: last }),
+            Some(dir) => Err(LoopError::DeadEnd{ at: last, towards: (last.0 + dir.0, last.1 + dir.1) }),
+        }
+    }
+
+    // closed loops of pipes which are not connected to the start
+    fn extra_loops(&self, on_loop: &[bool]) -> Vec<Vec<Coordinate>>{
+        let mut visited = on_loop.to_vec();
+        let mut loops = vec![];
+        for i in 0..self.tiles.len(){
+            let tile = self.tiles[i];
+            if visited[i] || !PIPES.contains(&tile){
+                continue
+            }
+            let xy = self.xy(i);
+            // walk away through one opening and see whether we come back through the other
+            let dir = DIRECTIONS.into_iter().find(|&dir| tile.next(dir).is_some()).unwrap();
+            let exit = tile.next(dir).unwrap();
+            let mut coords = vec![xy];
+            let mut from = (-exit.0, -exit.1);
+            let mut curr = (xy.0 + exit.0, xy.1 + exit.1);
+            visited[i] = true;
+            while let Some(&next_tile) = self.get_by_xy(curr){
+                let Some(next_dir) = next_tile.next(from) else { break };
+                let j = self.index(curr).unwrap();
+                if curr == xy{
+                    loops.push(coords.clone());
+                    break
+                }
+                if visited[j]{
+                    break
+                }
+                visited[j] = true;
+                coords.push(curr);
+                from = (-next_dir.0, -next_dir.1);
+                curr = (curr.0 + next_dir.0, curr.1 + next_dir.1);
+            }
+        }
+        loops
+    }
+
+    // tiles strictly inside the loop, by counting loop crossings from the left edge of each row
+    fn enclosed(&self, on_loop: &[bool]) -> Vec<bool>{
+        let start_tile = self.start_tile().ok();
+        let mut inside = vec![false; self.tiles.len()];
+        for y in 0..self.height{
+            let mut crossings = 0;
+            for x in 0..self.width{
+                let i = y * self.width + x;
+                if on_loop[i]{
+                    let tile = if self.tiles[i] == Start { start_tile.unwrap() } else { self.tiles[i] };
+                    // looking along the upper half of the row, only pipes going north are crossed
+                    if tile.next((0, -1)).is_some(){
+                        crossings += 1;
+                    }
+                }else{
+                    inside[i] = crossings % 2 == 1;
+                }
+            }
+        }
+        inside
+    }
+}
+
+#[derive(PartialEq, Debug)]
+enum LoopError{
+    NoStart,
+    // fewer than two neighbours connect to the start, so no loop can pass through it
+    StartNotConnected{ start: Coordinate, connections: Vec<Coordinate> },
+    // more than two neighbours connect to the start, so its pipe cannot be told
+    AmbiguousStart{ start: Coordinate, connections: Vec<Coordinate> },
+    // the pipe from the start ends at `at`, which leads to a tile not connecting back or off the map
+    DeadEnd{ at: Coordinate, towards: Coordinate },
+}
+
+impl fmt::Display for LoopError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self{
+            LoopError::NoStart => write!(f, "there is no start tile"),
+            LoopError::StartNotConnected{ start, connections } => write!(f, "start {:?} must connect to two pipes, but only connects to {:?}", start, connections),
+            LoopError::AmbiguousStart{ start, connections } => write!(f, "start {:?} must connect to exactly two pipes, but connects to {:?}", start, connections),
+            LoopError::DeadEnd{ at, towards } => write!(f, "the loop from the start ends at {:?}, nothing connects at {:?}", at, towards),
+        }
     }
+}
 
-    fn iter_from(&self, xy: Coordinate, from: (i64, i64)) -> PipeSegment{
-        return PipeSegment{
-            tilemap: &self, curr: (xy, *self.get_by_xy(xy).unwrap()), from
+impl fmt::Display for PuzzleInput {
+    // box drawing view: the loop is bold, other pipes are dimmed,
+    // enclosed ground is green and outside ground blue
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut on_loop = vec![false; self.tiles.len()];
+        for xy in self.trace_loop().unwrap_or_default(){
+            on_loop[self.index(xy).unwrap()] = true;
+        }
+        let inside = self.enclosed(&on_loop);
+        for y in 0..self.height{
+            for x in 0..self.width{
+                let i = y * self.width + x;
+                let tile = self.tiles[i];
+                let symbol = match tile{
+                    VPipe => '│',
+                    HPipe => '─',
+                    NEPipe => '└',
+                    NWPipe => '┘',
+                    SWPipe => '┐',
+                    SEPipe => '┌',
+                    Ground => if inside[i] { '•' } else { '·' },
+                    Start => 'S',
+                };
+                let style = match (tile, on_loop[i], inside[i]){
+                    (Start, _, _) => "\x1b[1;33m",
+                    (_, true, _) => "\x1b[1m",
+                    (Ground, _, true) => "\x1b[32m",
+                    (Ground, _, false) => "\x1b[34m",
+                    _ => "\x1b[2m",
+                };
+                write!(f, "{}{}\x1b[0m", style, symbol)?;
+            }
+            writeln!(f)?;
         }
+        Ok(())
     }
 }
 
@@ -143,40 +301,39 @@ fn parse_input(input: &str) -> PuzzleInput {
 
 
 fn solve_part1(input: &PuzzleInput) -> usize {
-    // find the start
-    let mut start_index: usize = 0;
-    for (i, t) in input.tiles.iter().enumerate(){
-        if *t == Tile::Start{
-            start_index = i;
-        }
-    }
-    let start_xy = input.xy(start_index);
-    let directions = [(1,0), (0, 1), (-1, 0), (0, -1)];
-    let mut begin = ((0, 0), (0, 0));
-    for dir in directions{
-        let xy = (start_xy.0 + dir.0, start_xy.1 + dir.1);
-        let tile = input.get_by_xy(xy);
-        if let Some(tile) = tile{
-            let from = (-dir.0, -dir.1); 
-            if let Some(next_dir) = tile.next(from){
-                begin = (xy, from);
-                break;
-            }
-        }
-    }
-    assert_ne!(begin.1, (0, 0), "invalid beginning");
-    let pipes = input.iter_from(begin.0, begin.1);
-    (pipes.count() + 1 ) / 2
+    input.find_loop().len() / 2
 }
 
-fn solve_part2(input: &PuzzleInput) -> u64 {
-    0
+fn solve_part2(input: &PuzzleInput) -> usize {
+    let coords = input.find_loop();
+    // shoelace formula gives twice the area enclosed by the loop's tile centres
+    let twice_area = coords.iter().zip(coords.iter().cycle().skip(1))
+        .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+        .sum::<i64>()
+        .unsigned_abs() as usize;
+    // Pick's theorem: area = interior + boundary / 2 - 1, a loop enclosing nothing has 2 * area = boundary - 2
+    (twice_area + 2 - coords.len()) / 2
 }
 
 fn main() {
     let input = include_str!("../input");
     let input = parse_input(input);
 
+    // `render` draws the map with the loop and the enclosed area highlighted
+    if std::env::args().nth(1).as_deref() == Some("render"){
+        return print!("{}", input);
+    }
+    let main_loop = match input.trace_loop(){
+        Ok(main_loop) => main_loop,
+        Err(e) => return println!("Invalid map: {}", e),
+    };
+    let mut on_loop = vec![false; input.tiles.len()];
+    for &xy in &main_loop{
+        on_loop[input.index(xy).unwrap()] = true;
+    }
+    for extra in input.extra_loops(&on_loop){
+        println!("Loop of {} pipes not connected to the start at {:?}", extra.len(), extra[0]);
+    }
     println!("Part 1: {}", solve_part1(&input));
     println!("Part 2: {}", solve_part2(&input));
 }
@@ -185,14 +342,40 @@ fn main() {
 mod tests {
     use super::*;
 
-    const EXAMPLE: &'static str = 
+    const EXAMPLE: &str = 
 "7-F7-
 .FJ|7
 SJLL7
 |F--J
 LJ.LJ";
 
-    use super::Tile::*;
+    // a loop squeezing between pipes, enclosing 8 tiles
+    const SQUEEZED_EXAMPLE: &str =
+".F----7F7F7F7F-7....
+.|F--7||||||||FJ....
+.||.FJ||||||||L7....
+FJL7L7LJLJ||LJ.L-7..
+L--J.L7...LJS7F-7L7.
+....F-J..F7FJ|L7L7L7
+....L7.F7||L7|.L7L7|
+.....|FJLJ|FJ|F7|.LJ
+....FJL-7.||.||||...
+....L---J.LJ.LJLJ...
+";
+
+    // a loop among many unconnected pipes, enclosing 10 tiles
+    const JUNK_EXAMPLE: &str =
+"FF7FSF7F7F7F7F7F---7
+L|LJ||||||||||||F--J
+FL-7LJLJ||||||LJL-77
+F--JF--7||LJLJ7F7FJ-
+L---JF-JLJ.||-FJLJJ7
+|F|F-JF---7F7-L7L|7|
+|FFJF7L7F-JF7|JL---7
+7-L-JL7||F7|L7F-7F7|
+L.L7LFJ|||||FJL7||LJ
+L7JLJL-JLJLJL--JLJ.L
+";
 
     macro_rules! example_parsed {
         () => {
@@ -213,7 +396,7 @@ LJ.LJ";
     #[test]
     fn test_parsing() {
         let input = example_parsed!();
-        assert_eq!(parse_input(&EXAMPLE), input);
+        assert_eq!(parse_input(EXAMPLE), input);
     }
 
     #[test]
@@ -236,4 +419,134 @@ LJ.LJ";
         let input = example_parsed!();
         assert_eq!(solve_part1(&input), 8);
     }
+
+    #[test]
+    fn test_start_tile() {
+        let input = example_parsed!();
+        assert_eq!(input.start_tile(), Ok(SEPipe));
+        let input = parse_input(".....\n.S-7.\n.|.|.\n.L-J.\n.....\n");
+        assert_eq!(input.start_tile(), Ok(SEPipe));
+        let input = parse_input("-7.\nLS.\n...\n");
+        assert_eq!(input.start_tile(), Ok(NWPipe));
+        let input = parse_input("F7.\n|S-\n...\n");
+        assert_eq!(input.start_tile(), Ok(NEPipe));
+        // three pipes lead into the start
+        let input = parse_input(".|.\n-S-\n...\n");
+        assert_eq!(input.start_tile(), Err(LoopError::AmbiguousStart{ start: (1, 1), connections: vec![(2, 1), (0, 1), (1, 0)] }));
+        let input = parse_input("...\n.S-\n...\n");
+        assert_eq!(input.start_tile(), Err(LoopError::StartNotConnected{ start: (1, 1), connections: vec![(2, 1)] }));
+        let input = parse_input("...\n.S.\n...\n");
+        assert_eq!(input.start_tile(), Err(LoopError::StartNotConnected{ start: (1, 1), connections: vec![] }));
+        let input = parse_input("...\n.--\n...\n");
+        assert_eq!(input.start_tile(), Err(LoopError::NoStart));
+    }
+
+    #[test]
+    fn test_solve_part2() {
+        let input = example_parsed!();
+        assert_eq!(solve_part2(&input), 1);
+
+        let input = parse_input(
+"...........
+.S-------7.
+.|F-----7|.
+.||.....||.
+.||.....||.
+.|L-7.F-J|.
+.|..|.|..|.
+.L--J.L--J.
+...........
+");
+        assert_eq!(solve_part2(&input), 4);
+
+        let input = parse_input(SQUEEZED_EXAMPLE);
+        assert_eq!(solve_part2(&input), 8);
+
+        let input = parse_input(JUNK_EXAMPLE);
+        assert_eq!(solve_part2(&input), 10);
+
+        // loops enclosing no tiles at all
+        let input = parse_input("S7\nLJ\n");
+        assert_eq!(solve_part2(&input), 0);
+        let input = parse_input("S---7\nL---J\n");
+        assert_eq!(solve_part2(&input), 0);
+    }
+
+    #[test]
+    fn test_enclosed() {
+        let input = parse_input(
+"...........
+.S-------7.
+.|F-----7|.
+.||.....||.
+.||.....||.
+.|L-7.F-J|.
+.|..|.|..|.
+.L--J.L--J.
+...........
+");
+        let mut on_loop = vec![false; input.tiles.len()];
+        for xy in input.find_loop(){
+            on_loop[input.index(xy).unwrap()] = true;
+        }
+        let inside = input.enclosed(&on_loop);
+        let enclosed: Vec<Coordinate> = (0..inside.len()).filter(|&i| inside[i]).map(|i| input.xy(i)).collect();
+        assert_eq!(enclosed, vec![(2, 6), (3, 6), (7, 6), (8, 6)]);
+
+        // the scanline agrees with shoelace and Pick where loops squeeze between pipes and junk lies around
+        for example in [EXAMPLE, SQUEEZED_EXAMPLE, JUNK_EXAMPLE]{
+            let input = parse_input(example);
+            let mut on_loop = vec![false; input.tiles.len()];
+            for xy in input.find_loop(){
+                on_loop[input.index(xy).unwrap()] = true;
+            }
+            let inside = input.enclosed(&on_loop);
+            assert_eq!(inside.iter().filter(|&&i| i).count(), solve_part2(&input));
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let input = parse_input("..F7.\n.FJ|.\nSJ.L7\n|F--J\nLJ.-.\n");
+        let rendered = format!("{}", input);
+        let plain: String = rendered.replace("\x1b[0m", "").replace("\x1b[1;33m", "").replace("\x1b[1m", "")
+            .replace("\x1b[2m", "").replace("\x1b[32m", "").replace("\x1b[34m", "");
+        assert_eq!(plain, "··┌┐·\n·┌┘│·\nS┘•└┐\n│┌──┘\n└┘·─·\n");
+        // the stray pipe at the bottom is dimmed, the loop is bold
+        assert!(rendered.contains("\x1b[2m─"));
+        assert!(rendered.contains("\x1b[1m┌"));
+        assert!(rendered.contains("\x1b[32m•"));
+    }
+
+    #[test]
+    fn test_dead_end() {
+        // the pipe right of the start turns south into ground
+        let input = parse_input(".....\n.S-7.\n.|...\n.L-J.\n.....\n");
+        assert_eq!(input.trace_loop(), Err(LoopError::DeadEnd{ at: (3, 1), towards: (3, 2) }));
+        // a pipe pointing off the map
+        let input = parse_input("S-7\n|.|\nL-|\n");
+        assert_eq!(input.trace_loop(), Err(LoopError::DeadEnd{ at: (2, 2), towards: (2, 3) }));
+        // the next pipe does not connect back
+        let input = parse_input("S-7\n|.|\nL--\n");
+        assert_eq!(input.trace_loop(), Err(LoopError::DeadEnd{ at: (2, 1), towards: (2, 2) }));
+    }
+
+    #[test]
+    fn test_extra_loops() {
+        let input = parse_input(
+"S-7F7.
+|.|LJ.
+L-J.F7
+F-7.LJ
+|.|...
+L-J.-7
+");
+        let main_loop = input.trace_loop().unwrap();
+        let mut on_loop = vec![false; input.tiles.len()];
+        for &xy in &main_loop{
+            on_loop[input.index(xy).unwrap()] = true;
+        }
+        let extra_loops = input.extra_loops(&on_loop);
+        assert_eq!(extra_loops.iter().map(|l| (l[0], l.len())).collect::<Vec<_>>(), vec![((3, 0), 4), ((4, 2), 4), ((0, 3), 8)]);
+    }
 }
@@ -18,83 +18,201 @@ fn parse_input(input: &str) -> PuzzleInput {
     PuzzleInput { observations }
 }
 
-fn expand(observations: &Vec<Coordinate>, factor: i64) -> Vec<Coordinate>{
-    let empty = |ax: &dyn Fn(&Coordinate) -> i64| -> Vec<i64>{
-        let max = observations.iter().map(ax).max().unwrap();
-        let mut empty = vec![];
-        for c in 0..max as usize{
-            let mut is_empty = true;
-            for o in observations.iter(){
-                if c as i64 == ax(o){
-                    is_empty = false;
-                    break
-                }
-            }
-            if is_empty{
-                empty.push(c as i64);
-            }
-        }
-        empty
-    };
-    let empty_columns = empty(&|(x, _)|{ *x});
-    let empty_rows = empty(&|(_, y)|{ *y});
-
-    let mut expanded = observations.clone();
-    for (i, o) in observations.iter().enumerate(){
-        for x in empty_columns.iter(){
-            if o.0 > *x{
-                expanded[i].0 += factor-1;
-            }else {
-                break;
-            }
-        }
-        for y in empty_rows.iter(){
-            if o.1 > *y{
-                expanded[i].1 += factor-1;
-            }else {
-                break;
-            }
+type ExpandedCoordinate = (u128, u128);
+
+// every empty line along an axis becomes `factor` lines
+fn expand_axis(values: &[i64], factor: u128) -> Vec<u128>{
+    let min = values.iter().copied().min().unwrap_or(0);
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by_key(|&i| values[i]);
+    let mut expanded = vec![0; values.len()];
+    let mut distinct = 0;
+    let mut previous = None;
+    for i in order{
+        if previous != Some(values[i]){
+            distinct += 1;
+            previous = Some(values[i]);
         }
+        // all lines between the smallest value and this one which hold no galaxy are empty
+        let offset = (values[i] - min) as u128;
+        let empty = offset + 1 - distinct;
+        expanded[i] = offset + empty * (factor - 1);
     }
     expanded
 }
 
-fn distance(a: &Coordinate, b: &Coordinate) -> usize{
-    ((a.0-b.0).abs() + (a.1-b.1).abs()) as usize
+// expands columns by `factors.0` and rows by `factors.1`, shifted so the first galaxy line is 0
+fn expand(observations: &[Coordinate], factors: (u128, u128)) -> Vec<ExpandedCoordinate>{
+    let xs: Vec<i64> = observations.iter().map(|o| o.0).collect();
+    let ys: Vec<i64> = observations.iter().map(|o| o.1).collect();
+    expand_axis(&xs, factors.0).into_iter().zip(expand_axis(&ys, factors.1)).collect()
+}
+
+fn distance(a: &ExpandedCoordinate, b: &ExpandedCoordinate) -> u128{
+    a.0.abs_diff(b.0) + a.1.abs_diff(b.1)
 }
 
-fn sum_expenaded_distances(input: &PuzzleInput, factor: i64) -> usize{
-    let expanded: Vec<Coordinate> = expand(&input.observations, factor);
+// sum of |a - b| over all pairs, from the sorted values and their running sum
+fn sum_pairwise_differences(values: &mut [u128]) -> u128{
+    values.sort();
+    let mut prefix = 0;
     let mut sum = 0;
-    for (i, icoord) in expanded.iter().enumerate(){
-        for jcoord in expanded[i+1..].iter(){
-            sum += distance(icoord, jcoord);
-        }
+    for (i, &v) in values.iter().enumerate(){
+        sum += v * i as u128 - prefix;
+        prefix += v;
     }
     sum
 }
 
-fn solve_part1(input: &PuzzleInput) -> usize {
-    sum_expenaded_distances(input, 2)
+// the manhattan distance splits into the axes, so each axis can be summed separately
+fn sum_expanded_distances(input: &PuzzleInput, factors: (u128, u128)) -> u128{
+    let expanded = expand(&input.observations, factors);
+    let mut xs: Vec<u128> = expanded.iter().map(|e| e.0).collect();
+    let mut ys: Vec<u128> = expanded.iter().map(|e| e.1).collect();
+    sum_pairwise_differences(&mut xs) + sum_pairwise_differences(&mut ys)
+}
+
+fn solve_part1(input: &PuzzleInput) -> u128 {
+    sum_expanded_distances(input, (2, 2))
+}
+
+fn solve_part2(input: &PuzzleInput) -> u128 {
+    sum_expanded_distances(input, (1000000, 1000000))
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Metric {
+    Manhattan,
+    Chebyshev,
+    Euclidean,
+}
+
+impl Metric {
+    fn distance(self, a: &ExpandedCoordinate, b: &ExpandedCoordinate) -> f64{
+        let (dx, dy) = (a.0.abs_diff(b.0), a.1.abs_diff(b.1));
+        match self{
+            Metric::Manhattan => distance(a, b) as f64,
+            Metric::Chebyshev => dx.max(dy) as f64,
+            Metric::Euclidean => (dx as f64).hypot(dy as f64),
+        }
+    }
+}
+
+// 2d tree over the expanded galaxies, stored implicitly: the median of every slice of `order` is
+// its node, splitting on x at even depths and on y at odd ones
+struct GalaxyIndex {
+    galaxies: Vec<ExpandedCoordinate>,
+    order: Vec<usize>,
+}
+
+impl GalaxyIndex {
+    fn new(galaxies: Vec<ExpandedCoordinate>) -> Self{
+        fn build(galaxies: &[ExpandedCoordinate], order: &mut [usize], depth: usize){
+            if order.len() <= 1{
+                return
+            }
+            let mid = order.len() / 2;
+            order.select_nth_unstable_by_key(mid, |&g| axis(&galaxies[g], depth));
+            let (lower, upper) = order.split_at_mut(mid);
+            build(galaxies, lower, depth + 1);
+            build(galaxies, &mut upper[1..], depth + 1);
+        }
+        let mut order: Vec<usize> = (0..galaxies.len()).collect();
+        build(&galaxies, &mut order, 0);
+        GalaxyIndex { galaxies, order }
+    }
+
+    // the `k` galaxies closest to `galaxy`, nearest first, ties broken by the lower index
+    fn k_nearest(&self, galaxy: usize, k: usize, metric: Metric) -> Vec<(usize, f64)>{
+        fn search(index: &GalaxyIndex, range: std::ops::Range<usize>, depth: usize, query: usize, k: usize, metric: Metric, best: &mut Vec<(usize, f64)>){
+            if range.is_empty(){
+                return
+            }
+            let mid = range.start + range.len() / 2;
+            let node = index.order[mid];
+            let q = &index.galaxies[query];
+            if node != query{
+                let d = metric.distance(q, &index.galaxies[node]);
+                let position = best.partition_point(|&(g, bd)| bd < d || (bd == d && g < node));
+                if position < k{
+                    best.insert(position, (node, d));
+                    best.truncate(k);
+                }
+            }
+            let split = axis(&index.galaxies[node], depth);
+            let here = axis(q, depth);
+            let (near, far) = if here < split{
+                (range.start..mid, mid + 1..range.end)
+            }else{
+                (mid + 1..range.end, range.start..mid)
+            };
+            search(index, near, depth + 1, query, k, metric, best);
+            // every metric is at least as large as the difference along one axis
+            let plane = here.abs_diff(split) as f64;
+            if best.len() < k || plane <= best.last().unwrap().1{
+                search(index, far, depth + 1, query, k, metric, best);
+            }
+        }
+        let mut best = vec![];
+        if k == 0{
+            return best
+        }
+        search(self, 0..self.order.len(), 0, galaxy, k, metric, &mut best);
+        best
+    }
+
+    fn nearest(&self, galaxy: usize, metric: Metric) -> Option<(usize, f64)>{
+        self.k_nearest(galaxy, 1, metric).first().copied()
+    }
 }
 
-fn solve_part2(input: &PuzzleInput) -> usize {
-    sum_expenaded_distances(input, 1000000)
+fn axis(c: &ExpandedCoordinate, depth: usize) -> u128{
+    if depth.is_multiple_of(2) { c.0 } else { c.1 }
+}
+
+impl PuzzleInput {
+    fn galaxy_index(&self, factors: (u128, u128)) -> GalaxyIndex{
+        GalaxyIndex::new(expand(&self.observations, factors))
+    }
+
+    // nearest other galaxy and its distance for every galaxy
+    fn nearest_neighbours(&self, factors: (u128, u128), metric: Metric) -> Vec<Option<(usize, f64)>>{
+        let index = self.galaxy_index(factors);
+        (0..self.observations.len()).map(|g| index.nearest(g, metric)).collect()
+    }
 }
 
 fn main() {
     let input = include_str!("../input");
     let input = parse_input(input);
 
-    println!("Part 1: {}", solve_part1(&input));
-    println!("Part 2: {}", solve_part2(&input));
+    // `closest [factor]` prints the closest pair of galaxies under every metric instead of solving
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) != Some("closest"){
+        println!("Part 1: {}", solve_part1(&input));
+        println!("Part 2: {}", solve_part2(&input));
+        return;
+    }
+    let Some(factor) = args.get(2).map_or(Some(1000000), |a| a.parse::<u128>().ok().filter(|&f| f > 0)) else{
+        eprintln!("usage: day11 closest [factor]");
+        return;
+    };
+    for metric in [Metric::Manhattan, Metric::Chebyshev, Metric::Euclidean]{
+        let neighbours = input.nearest_neighbours((factor, factor), metric);
+        let closest = neighbours.iter().enumerate()
+            .filter_map(|(g, n)| n.map(|(other, d)| (g, other, d)))
+            .min_by(|a, b| a.2.total_cmp(&b.2));
+        if let Some((a, b, d)) = closest{
+            println!("Closest galaxies by {:?} distance: {:?} and {:?}, {}", metric, input.observations[a], input.observations[b], d);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const EXAMPLE: &'static str = 
+    const EXAMPLE: &str = 
 "...#......
 .......#..
 #.........
@@ -120,7 +238,7 @@ mod tests {
     #[test]
     fn test_parsing() {
         let input = example_parsed!();
-        assert_eq!(parse_input(&EXAMPLE), input);
+        assert_eq!(parse_input(EXAMPLE), input);
     }
 
     #[test]
@@ -132,9 +250,22 @@ mod tests {
     #[test]
     fn test_expansion() {
         let input = example_parsed!();
-        assert_eq!(expand(&input.observations, 1), vec![
+        assert_eq!(expand(&input.observations, (2, 2)), vec![
             (4, 0), (9, 1), (0, 2), (8, 5), (1, 6), (12, 7), (9, 10), (0, 11), (5, 11)
         ]);
+        assert_eq!(expand(&input.observations, (1, 1)), vec![
+            (3, 0), (7, 1), (0, 2), (6, 4), (1, 5), (9, 6), (7, 8), (0, 9), (4, 9)
+        ]);
+        // only the columns grow
+        assert_eq!(expand(&input.observations, (3, 1)), vec![
+            (5, 0), (11, 1), (0, 2), (10, 4), (1, 5), (15, 6), (11, 8), (0, 9), (6, 9)
+        ]);
+    }
+
+    #[test]
+    fn test_expansion_offset() {
+        // coordinates are shifted so the first column and row holding a galaxy is 0
+        assert_eq!(expand(&[(5, -3), (8, 2), (5, 2)], (10, 2)), vec![(0, 0), (21, 9), (0, 9)]);
     }
 
     #[test]
@@ -146,7 +277,69 @@ mod tests {
     #[test]
     fn test_solve_part2() {
         let input = example_parsed!();
-        assert_eq!(sum_expenaded_distances(&input, 10), 1030);
-        assert_eq!(sum_expenaded_distances(&input, 100), 8410);
+        assert_eq!(sum_expanded_distances(&input, (10, 10)), 1030);
+        assert_eq!(sum_expanded_distances(&input, (100, 100)), 8410);
+    }
+
+    #[test]
+    fn test_sum_matches_pairs() {
+        let input = example_parsed!();
+        for factors in [(1, 1), (2, 5), (7, 3)] {
+            let expanded = expand(&input.observations, factors);
+            let mut sum = 0;
+            for (i, a) in expanded.iter().enumerate(){
+                for b in expanded[i+1..].iter(){
+                    sum += distance(a, b);
+                }
+            }
+            assert_eq!(sum_expanded_distances(&input, factors), sum);
+        }
+    }
+
+    #[test]
+    fn test_huge_factors() {
+        let input = example_parsed!();
+        // every empty line between two galaxies adds factor - 1 to their distance
+        let factor = 10u128.pow(30);
+        let small = sum_expanded_distances(&input, (2, 2)) - sum_expanded_distances(&input, (1, 1));
+        assert_eq!(sum_expanded_distances(&input, (factor, factor)), sum_expanded_distances(&input, (1, 1)) + small * (factor - 1));
+    }
+
+    #[test]
+    fn test_metrics() {
+        assert_eq!(Metric::Manhattan.distance(&(1, 5), &(4, 9)), 7.0);
+        assert_eq!(Metric::Chebyshev.distance(&(1, 5), &(4, 9)), 4.0);
+        assert_eq!(Metric::Euclidean.distance(&(1, 5), &(4, 9)), 5.0);
+    }
+
+    #[test]
+    fn test_nearest_neighbours() {
+        let input = example_parsed!();
+        let index = input.galaxy_index((2, 2));
+        for metric in [Metric::Manhattan, Metric::Chebyshev, Metric::Euclidean]{
+            for g in 0..input.observations.len(){
+                // compare the tree against sorting all other galaxies
+                let mut expected: Vec<(usize, f64)> = (0..input.observations.len()).filter(|&o| o != g)
+                    .map(|o| (o, metric.distance(&index.galaxies[g], &index.galaxies[o]))).collect();
+                expected.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+                for k in [0, 1, 3, 8, 20]{
+                    let mut expected = expected.clone();
+                    expected.truncate(k);
+                    assert_eq!(index.k_nearest(g, k, metric), expected);
+                }
+            }
+        }
+        // galaxy 4 expands to (1, 6) and is closest to galaxy 2 at (0, 2), galaxies 7 and 8 share a row
+        let neighbours = input.nearest_neighbours((2, 2), Metric::Manhattan);
+        assert_eq!(neighbours[4], Some((2, 5.0)));
+        assert_eq!(neighbours[5], Some((3, 6.0)));
+        assert_eq!(neighbours[7], Some((8, 5.0)));
+        assert_eq!(input.nearest_neighbours((2, 2), Metric::Chebyshev)[4], Some((2, 4.0)));
+    }
+
+    #[test]
+    fn test_nearest_single_galaxy() {
+        let input = PuzzleInput { observations: vec![(3, 3)] };
+        assert_eq!(input.nearest_neighbours((2, 2), Metric::Euclidean), vec![None]);
     }
 }
@@ -1,4 +1,6 @@
-use std::str;
+#[path = "../../common/rng.rs"]
+mod rng;
+use rng::Rng;
 
 #[derive(PartialEq, Debug)]
 struct Row<'a> {
@@ -11,130 +13,476 @@ struct PuzzleInput<'a> {
     rows: Vec<Row<'a>>,
 }
 
-fn parse_row(line: &str) -> Row {
+fn parse_row(line: &str) -> Row<'_> {
     let mut parts = line.split_whitespace();
     let springs = parts.next().unwrap();
-    let records = parts.next().unwrap();
-    let groups = records
+    let groups = parse_groups(parts.next().unwrap());
+    Row { springs, groups }
+}
+
+fn parse_groups(records: &str) -> Vec<usize> {
+    // an empty record is a line without any '#'
+    records
         .split(',')
+        .filter(|s| !s.is_empty())
         .map(|s| s.parse::<usize>().unwrap())
-        .collect();
-    Row { springs, groups }
+        .collect()
 }
 
-fn parse_input(input: &str) -> PuzzleInput {
+fn parse_input(input: &str) -> PuzzleInput<'_> {
     let rows = input.lines().map(parse_row).collect();
     PuzzleInput { rows }
 }
 
-fn arrangements(parent_springs: &[u8], offset: usize, groups: &[usize]) -> usize {
-    let mut springs = Vec::from(parent_springs);
-    // gather matches
-    let n = groups[0];
-    //println!("{} {} {:?}", str::from_utf8(springs.as_slice()).unwrap(), offset, groups);
-    let check_next = |springs: &[u8], i: usize| -> usize {
-        if groups.len() > 1 {
-            arrangements(springs, i, &groups[1..])
+// (number of completed groups, length of the current run of '#')
+type State = (usize, usize);
+
+// the states reached by putting '.' or '#' on a spring showing `c`, with the spring put there
+fn transitions(c: u8, (g, r): State, groups: &[usize]) -> impl Iterator<Item = (u8, State)> {
+    if !b".#?".contains(&c) {
+        panic!("invalid character {}", c as char);
+    }
+    let operational = if c == b'#' {
+        None
+    } else if r == 0 {
+        Some((b'.', (g, 0)))
+    } else if g < groups.len() && r == groups[g] {
+        // the run ends and completes its group
+        Some((b'.', (g + 1, 0)))
+    } else {
+        None
+    };
+    let damaged = (c != b'.' && g < groups.len() && r < groups[g]).then_some((b'#', (g, r + 1)));
+    operational.into_iter().chain(damaged)
+}
+
+fn is_final((g, r): State, groups: &[usize]) -> bool {
+    // the springs may end in the middle of the last run
+    (g == groups.len() && r == 0) || (g + 1 == groups.len() && r == groups[g])
+}
+
+// the row has more arrangements than fit in a u128, so they cannot be counted or weighed exactly
+#[derive(PartialEq, Debug)]
+struct TooManyArrangements;
+
+// counts the ways to fill in the '?' so the runs of '#' are exactly `groups`
+// walks the springs once, keeping how many ways lead to each (group index, run length)
+fn arrangements(springs: &[u8], groups: &[usize]) -> Result<u128, TooManyArrangements> {
+    let max_run = groups.iter().copied().max().unwrap_or(0);
+    // ways[g][r]: g groups are complete and the current run of '#' is r long
+    let mut ways = vec![vec![0u128; max_run + 1]; groups.len() + 1];
+    ways[0][0] = 1;
+    for &c in springs {
+        let mut next = vec![vec![0; max_run + 1]; groups.len() + 1];
+        for (g, runs) in ways.iter().enumerate() {
+            for (r, &count) in runs.iter().enumerate() {
+                if count == 0 {
+                    continue;
+                }
+                for (_, (ng, nr)) in transitions(c, (g, r), groups) {
+                    next[ng][nr] = count.checked_add(next[ng][nr]).ok_or(TooManyArrangements)?;
+                }
+            }
+        }
+        ways = next;
+    }
+    let mut total: u128 = 0;
+    for (g, runs) in ways.iter().enumerate() {
+        for (r, &count) in runs.iter().enumerate() {
+            if is_final((g, r), groups) {
+                total = total.checked_add(count).ok_or(TooManyArrangements)?;
+            }
+        }
+    }
+    Ok(total)
+}
+
+// completable[pos][g][r]: springs[pos..] can be filled in when arriving there in state (g, r)
+// booleans rather than counts, long rows have more arrangements than fit in any fixed size integer
+fn completable(springs: &[u8], groups: &[usize]) -> Vec<Vec<Vec<bool>>> {
+    let max_run = groups.iter().copied().max().unwrap_or(0);
+    let mut completable = vec![vec![vec![false; max_run + 1]; groups.len() + 1]; springs.len() + 1];
+    for (g, runs) in completable[springs.len()].iter_mut().enumerate() {
+        for (r, possible) in runs.iter_mut().enumerate() {
+            *possible = is_final((g, r), groups);
+        }
+    }
+    for pos in (0..springs.len()).rev() {
+        let (before, after) = completable.split_at_mut(pos + 1);
+        for (g, runs) in before[pos].iter_mut().enumerate() {
+            for (r, possible) in runs.iter_mut().enumerate() {
+                *possible =
+                    transitions(springs[pos], (g, r), groups).any(|(_, (ng, nr))| after[0][ng][nr]);
+            }
+        }
+    }
+    completable
+}
+
+// completions[pos][g][r]: ways to fill springs[pos..] when arriving there in state (g, r)
+fn completions(
+    springs: &[u8],
+    groups: &[usize],
+) -> Result<Vec<Vec<Vec<u128>>>, TooManyArrangements> {
+    let max_run = groups.iter().copied().max().unwrap_or(0);
+    let table =
+        |count: &dyn Fn(State) -> Option<u128>| -> Result<Vec<Vec<u128>>, TooManyArrangements> {
+            (0..=groups.len())
+                .map(|g| {
+                    (0..=max_run)
+                        .map(|r| count((g, r)).ok_or(TooManyArrangements))
+                        .collect()
+                })
+                .collect()
+        };
+    let mut completions = vec![table(&|state| Some(is_final(state, groups) as u128))?];
+    for &c in springs.iter().rev() {
+        let after = completions.last().unwrap();
+        let before = table(&|state| {
+            transitions(c, state, groups)
+                .try_fold(0u128, |sum, (_, (ng, nr))| sum.checked_add(after[ng][nr]))
+        })?;
+        completions.push(before);
+    }
+    // built from the end backwards
+    completions.reverse();
+    Ok(completions)
+}
+
+// yields every filled in row, preferring '.' over '#' from left to right
+// dead ends are never entered, so each arrangement costs one walk over the springs
+struct ArrangementIter<'a> {
+    springs: &'a [u8],
+    groups: &'a [usize],
+    completable: Vec<Vec<Vec<bool>>>,
+    // partially filled springs and the state after them
+    stack: Vec<(Vec<u8>, State)>,
+}
+
+impl<'a> ArrangementIter<'a> {
+    fn new(springs: &'a [u8], groups: &'a [usize]) -> Self {
+        let completable = completable(springs, groups);
+        let stack = if completable[0][0][0] {
+            vec![(vec![], (0, 0))]
         } else {
-            println!("{} [DONE]", str::from_utf8(springs).unwrap());
-            1
+            vec![]
+        };
+        ArrangementIter {
+            springs,
+            groups,
+            completable,
+            stack,
         }
-    };
-    let mut arr = 0;
-    let pattern_matches = |springs: &[u8], start: usize| -> Option<(Vec<u8>, usize)> {
-        let mut new_springs = Vec::from(springs);
-        if start + n > springs.len() {
-            // not enough space for this group
-            return None;
+    }
+}
+
+impl Iterator for ArrangementIter<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((filled, state)) = self.stack.pop() {
+            let pos = filled.len();
+            if pos == self.springs.len() {
+                return Some(String::from_utf8(filled).unwrap());
+            }
+            let options: Vec<(u8, State)> = transitions(self.springs[pos], state, self.groups)
+                .filter(|&(_, (g, r))| self.completable[pos + 1][g][r])
+                .collect();
+            // the stack is last in first out
+            for &(placed, next_state) in options.iter().rev() {
+                let mut next = filled.clone();
+                next.push(placed);
+                self.stack.push((next, next_state));
+            }
         }
-        let leftover_damaged = groups.len()-1 + groups.iter().sum::<usize>();
-        if leftover_damaged + start > springs.len() {
-            // not enough space for other groups
+        None
+    }
+}
+
+// draws arrangements uniformly: every choice is weighted by how many arrangements follow from it
+struct ArrangementSampler<'a> {
+    springs: &'a [u8],
+    groups: &'a [usize],
+    completions: Vec<Vec<Vec<u128>>>,
+    rng: Rng,
+}
+
+impl<'a> ArrangementSampler<'a> {
+    fn new(springs: &'a [u8], groups: &'a [usize], seed: u64) -> Result<Self, TooManyArrangements> {
+        Ok(ArrangementSampler {
+            springs,
+            groups,
+            completions: completions(springs, groups)?,
+            rng: Rng(seed),
+        })
+    }
+
+    fn sample(&mut self) -> Option<String> {
+        if self.completions[0][0][0] == 0 {
             return None;
         }
-        // there is still space for the group
-        let mut ok = true;
-        for j in 0..n {
-            if springs[start + j] == b'.' {
-                // the group finished
-                ok = false;
-                break;
-            }else{
-                new_springs[start + j] = b'#';
-            }
-        }
-        if ok {
-            // the group can be full of '#'
-            if springs.len() > start + n {
-                // it's not over yet. There need to be a '.' after the group
-                if springs[start + n] != b'#' {
-                    // there is no '#' after the group
-                    new_springs[start + n] = b'.';
-                    Some((new_springs, start + n + 1))
-                }else{
-                    // there is a '#' which means it's connecting with another group (bad)
-                    None
+        let mut filled = Vec::with_capacity(self.springs.len());
+        let mut state = (0, 0);
+        for pos in 0..self.springs.len() {
+            let (g, r) = state;
+            let mut pick = self.rng.below(self.completions[pos][g][r]);
+            for (placed, (ng, nr)) in transitions(self.springs[pos], state, self.groups) {
+                let weight = self.completions[pos + 1][ng][nr];
+                if pick < weight {
+                    filled.push(placed);
+                    state = (ng, nr);
+                    break;
                 }
-            } else {
-                // it's at the end of the springs list. finished
-                Some((new_springs, start + n))
+                pick -= weight;
             }
-        }else{
-            // pattern doesn't match
-            None
         }
-    };
-    for (i, c) in parent_springs.iter().enumerate().skip(offset) {
-        match c {
-            b'#' => {
-                if let Some((filled_springs, next_starting_index)) = pattern_matches(springs.as_slice(), i) {
-                    arr += check_next(filled_springs.as_slice(), next_starting_index);
+        Some(String::from_utf8(filled).unwrap())
+    }
+}
+
+impl Row<'_> {
+    fn arrangements(&self) -> ArrangementIter<'_> {
+        ArrangementIter::new(self.springs.as_bytes(), &self.groups)
+    }
+
+    fn sampler(&self, seed: u64) -> Result<ArrangementSampler<'_>, TooManyArrangements> {
+        ArrangementSampler::new(self.springs.as_bytes(), &self.groups, seed)
+    }
+}
+
+// part 2 has the springs five times, separated by '?', and the groups five times
+fn unfold(row: &Row, times: usize) -> (String, Vec<usize>) {
+    let springs = vec![row.springs; times].join("?");
+    let groups = row.groups.repeat(times);
+    (springs, groups)
+}
+
+fn solve_part1(input: &PuzzleInput) -> Result<u128, TooManyArrangements> {
+    input.rows.iter().try_fold(0u128, |sum, r| {
+        let count = arrangements(r.springs.as_bytes(), &r.groups)?;
+        sum.checked_add(count).ok_or(TooManyArrangements)
+    })
+}
+
+fn solve_part2(input: &PuzzleInput) -> Result<u128, TooManyArrangements> {
+    input.rows.iter().try_fold(0u128, |sum, r| {
+        let (springs, groups) = unfold(r, 5);
+        let count = arrangements(springs.as_bytes(), &groups)?;
+        sum.checked_add(count).ok_or(TooManyArrangements)
+    })
+}
+
+// a nonogram is a grid where every row and every column is a row of springs
+// cells are b'#', b'.' or b'?' while still unknown
+type Grid = Vec<Vec<u8>>;
+
+struct Nonogram {
+    rows: Vec<Vec<usize>>,
+    columns: Vec<Vec<usize>>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Solutions {
+    None,
+    Unique(Grid),
+    // two of possibly many more
+    Multiple(Grid, Grid),
+}
+
+// fixes every cell of a line which has the same value in all arrangements
+// None if the line has no arrangement left
+fn refine_line(line: &[u8], groups: &[usize]) -> Option<Vec<u8>> {
+    let max_run = groups.iter().copied().max().unwrap_or(0);
+    let completable = completable(line, groups);
+    if !completable[0][0][0] {
+        return None;
+    }
+    // walk forwards through the states which can still be completed, noting what each cell can be
+    let mut reachable = vec![vec![false; max_run + 1]; groups.len() + 1];
+    reachable[0][0] = true;
+    let mut refined = Vec::with_capacity(line.len());
+    for pos in 0..line.len() {
+        let mut next = vec![vec![false; max_run + 1]; groups.len() + 1];
+        let (mut operational, mut damaged) = (false, false);
+        for (g, runs) in reachable.iter().enumerate() {
+            for (r, _) in runs.iter().enumerate().filter(|(_, &reached)| reached) {
+                for (placed, (ng, nr)) in transitions(line[pos], (g, r), groups) {
+                    if completable[pos + 1][ng][nr] {
+                        next[ng][nr] = true;
+                        if placed == b'#' {
+                            damaged = true;
+                        } else {
+                            operational = true;
+                        }
+                    }
                 }
-                break;
             }
-            b'.' => {
-                continue;
+        }
+        refined.push(match (operational, damaged) {
+            (true, true) => b'?',
+            (true, false) => b'.',
+            _ => b'#',
+        });
+        reachable = next;
+    }
+    Some(refined)
+}
+
+impl Nonogram {
+    fn parse(input: &str) -> Nonogram {
+        // row clues, an empty line, then column clues, one "1,3,1" per line
+        let (rows, columns) = input
+            .split_once("\n\n")
+            .expect("no empty line between rows and columns");
+        Nonogram {
+            rows: rows.lines().map(parse_groups).collect(),
+            columns: columns.lines().map(parse_groups).collect(),
+        }
+    }
+
+    // refines rows and columns in turn until nothing changes, false on a contradiction
+    fn propagate(&self, grid: &mut Grid) -> bool {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (y, groups) in self.rows.iter().enumerate() {
+                let Some(refined) = refine_line(&grid[y], groups) else {
+                    return false;
+                };
+                changed |= refined != grid[y];
+                grid[y] = refined;
+            }
+            for (x, groups) in self.columns.iter().enumerate() {
+                let column: Vec<u8> = grid.iter().map(|row| row[x]).collect();
+                let Some(refined) = refine_line(&column, groups) else {
+                    return false;
+                };
+                changed |= refined != column;
+                for (row, cell) in grid.iter_mut().zip(refined) {
+                    row[x] = cell;
+                }
             }
-            b'?' => {
-                if let Some((filled_springs, next_starting_index)) = pattern_matches(springs.as_slice(), i) {
-                    arr += check_next(filled_springs.as_slice(), next_starting_index);
+        }
+        true
+    }
+
+    // propagates, then guesses the first unknown cell when propagation stalls
+    // stops once `limit` solutions are found
+    fn search(&self, mut grid: Grid, found: &mut Vec<Grid>, limit: usize) {
+        if found.len() >= limit || !self.propagate(&mut grid) {
+            return;
+        }
+        let unknown = grid
+            .iter()
+            .enumerate()
+            .find_map(|(y, row)| row.iter().position(|&c| c == b'?').map(|x| (y, x)));
+        match unknown {
+            None => found.push(grid),
+            Some((y, x)) => {
+                for guess in [b'#', b'.'] {
+                    let mut guessed = grid.clone();
+                    guessed[y][x] = guess;
+                    self.search(guessed, found, limit);
                 }
-                springs[i] = b'.';
             }
-            _ => panic!("invalid character {c}"),
         }
     }
-    arr
-}
 
-fn solve_part1(input: &PuzzleInput) -> usize {
-    input
-        .rows
-        .iter()
-        .map(|r| {
-            println!("{} {:?}", str::from_utf8(r.springs.as_bytes()).unwrap(), &r.groups);
-            arrangements(r.springs.as_bytes(), 0, &r.groups)
-        })
-        .sum()
+    fn solve(&self) -> Solutions {
+        let grid = vec![vec![b'?'; self.columns.len()]; self.rows.len()];
+        let mut found = vec![];
+        // a second solution is all it takes to tell it is not unique
+        self.search(grid, &mut found, 2);
+        let mut found = found.into_iter();
+        match (found.next(), found.next()) {
+            (None, _) => Solutions::None,
+            (Some(solution), None) => Solutions::Unique(solution),
+            (Some(first), Some(second)) => Solutions::Multiple(first, second),
+        }
+    }
 }
 
-fn solve_part2(input: &PuzzleInput) -> usize {
-    0
+fn grid_to_string(grid: &Grid) -> String {
+    grid.iter()
+        .map(|row| String::from_utf8(row.clone()).unwrap() + "\n")
+        .collect()
 }
 
 fn main() {
     let input = include_str!("../input");
     let input = parse_input(input);
 
-    println!("Part 1: {}", solve_part1(&input));
-    println!("Part 2: {}", solve_part2(&input));
+    // `list <row>` prints every arrangement of a row, `sample <row> <count>` random ones
+    // `nonogram <file>` solves a nonogram given its clues
+    let args: Vec<String> = std::env::args().collect();
+    // rows are numbered from 1 like the lines of the input
+    let row = |i: usize| -> Option<&Row> {
+        let number = args.get(i)?.parse::<usize>().ok()?;
+        input.rows.get(number.checked_sub(1)?)
+    };
+    match args.get(1).map(String::as_str) {
+        Some("list") => {
+            let Some(row) = row(2) else {
+                eprintln!("usage: day12 list <row>");
+                return;
+            };
+            for arrangement in row.arrangements() {
+                println!("{}", arrangement);
+            }
+            return;
+        }
+        Some("sample") => {
+            let count = args.get(3).and_then(|count| count.parse::<usize>().ok());
+            let (Some(row), Some(count)) = (row(2), count) else {
+                eprintln!("usage: day12 sample <row> <count>");
+                return;
+            };
+            let Ok(mut sampler) = row.sampler(2023) else {
+                eprintln!("too many arrangements to sample");
+                return;
+            };
+            for _ in 0..count {
+                println!("{}", sampler.sample().unwrap());
+            }
+            return;
+        }
+        Some("nonogram") => {
+            let Some(path) = args.get(2) else {
+                eprintln!("usage: day12 nonogram <file>");
+                return;
+            };
+            let clues = match std::fs::read_to_string(path) {
+                Ok(clues) => clues,
+                Err(e) => return eprintln!("cannot read {}: {}", path, e),
+            };
+            match Nonogram::parse(&clues).solve() {
+                Solutions::None => println!("no solution"),
+                Solutions::Unique(grid) => print!("unique solution\n{}", grid_to_string(&grid)),
+                Solutions::Multiple(first, second) => print!(
+                    "several solutions, two of them\n{}\n{}",
+                    grid_to_string(&first),
+                    grid_to_string(&second)
+                ),
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    match (solve_part1(&input), solve_part2(&input)) {
+        (Ok(part1), Ok(part2)) => {
+            println!("Part 1: {}", part1);
+            println!("Part 2: {}", part2);
+        }
+        _ => eprintln!("too many arrangements to count"),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const EXAMPLE: &'static str = "???.### 1,1,3
+    const EXAMPLE: &str = "???.### 1,1,3
 .??..??...?##. 1,1,3
 ?#?#?#?#?#?#?#? 1,3,1,6
 ????.#...#... 4,1,1
@@ -174,11 +522,11 @@ mod tests {
                     },
                     Row {
                         springs: ".?????...?",
-                        groups: vec![1,1,1],
+                        groups: vec![1, 1, 1],
                     },
                     Row {
                         springs: "#????????.#?#??????",
-                        groups: vec![2,1,1,5,1],
+                        groups: vec![2, 1, 1, 5, 1],
                     },
                 ],
             }
@@ -188,32 +536,214 @@ mod tests {
     #[test]
     fn test_parsing() {
         let input = example_parsed!();
-        assert_eq!(parse_input(&EXAMPLE), input);
+        assert_eq!(parse_input(EXAMPLE), input);
     }
 
     #[test]
     fn test_arrangements() {
         let input = example_parsed!();
-        let arr = |i: usize| arrangements(&input.rows[i].springs.as_bytes(),0, &input.rows[i].groups);
-        assert_eq!(arr(0), 1);
-        assert_eq!(arr(1), 4);
-        assert_eq!(arr(2), 1);
-        assert_eq!(arr(3), 1);
-        assert_eq!(arr(4), 4);
-        assert_eq!(arr(5), 10);
-        assert_eq!(arr(6), 7);
-        assert_eq!(arr(7), 36);
+        let arr = |i: usize| arrangements(input.rows[i].springs.as_bytes(), &input.rows[i].groups);
+        assert_eq!(arr(0), Ok(1));
+        assert_eq!(arr(1), Ok(4));
+        assert_eq!(arr(2), Ok(1));
+        assert_eq!(arr(3), Ok(1));
+        assert_eq!(arr(4), Ok(4));
+        assert_eq!(arr(5), Ok(10));
+        assert_eq!(arr(6), Ok(7));
+        assert_eq!(arr(7), Ok(36));
+    }
+
+    #[test]
+    fn test_arrangements_edge_cases() {
+        assert_eq!(arrangements(b"", &[]), Ok(1));
+        assert_eq!(arrangements(b"...", &[]), Ok(1));
+        assert_eq!(arrangements(b"?#?", &[]), Ok(0));
+        assert_eq!(arrangements(b"???", &[]), Ok(1));
+        assert_eq!(arrangements(b"", &[1]), Ok(0));
+        assert_eq!(arrangements(b"###", &[3]), Ok(1));
+        assert_eq!(arrangements(b"####", &[3]), Ok(0));
+        assert_eq!(arrangements(b"?????", &[1, 1]), Ok(6));
+    }
+
+    #[test]
+    fn test_unfold() {
+        let row = parse_row(".# 1");
+        assert_eq!(
+            unfold(&row, 5),
+            (".#?.#?.#?.#?.#".to_string(), vec![1, 1, 1, 1, 1])
+        );
     }
 
     #[test]
     fn test_solve_part1() {
-        let input = example_parsed!();
-        assert_eq!(solve_part1(&input), 21);
+        let mut input = example_parsed!();
+        // the last two rows are not part of the puzzle's example
+        input.rows.truncate(6);
+        assert_eq!(solve_part1(&input), Ok(21));
     }
 
     #[test]
     fn test_solve_part2() {
+        let mut input = example_parsed!();
+        input.rows.truncate(6);
+        assert_eq!(solve_part2(&input), Ok(525152));
+        let unfolded = |i: usize| {
+            let (springs, groups) = unfold(&input.rows[i], 5);
+            arrangements(springs.as_bytes(), &groups)
+        };
+        assert_eq!(unfolded(0), Ok(1));
+        assert_eq!(unfolded(1), Ok(16384));
+        assert_eq!(unfolded(5), Ok(506250));
+    }
+
+    #[test]
+    fn test_arrangement_iter() {
+        let input = example_parsed!();
+        assert_eq!(
+            input.rows[1].arrangements().collect::<Vec<_>>(),
+            vec![
+                "..#...#...###.",
+                "..#..#....###.",
+                ".#....#...###.",
+                ".#...#....###.",
+            ]
+        );
+        for row in &input.rows {
+            let all: Vec<String> = row.arrangements().collect();
+            assert_eq!(
+                Ok(all.len() as u128),
+                arrangements(row.springs.as_bytes(), &row.groups)
+            );
+            let mut unique = all.clone();
+            unique.sort();
+            unique.dedup();
+            assert_eq!(unique.len(), all.len());
+            for arrangement in &all {
+                // a filled row has exactly one arrangement: itself
+                assert_eq!(arrangements(arrangement.as_bytes(), &row.groups), Ok(1));
+                assert!(arrangement
+                    .bytes()
+                    .zip(row.springs.bytes())
+                    .all(|(a, s)| s == b'?' || a == s));
+            }
+        }
+        assert_eq!(parse_row("#.# 1").arrangements().next(), None);
+    }
+
+    #[test]
+    fn test_arrangement_iter_is_lazy() {
+        // far too many arrangements to collect, but the first ones come instantly
+        let (springs, groups) = unfold(&parse_row("???????????? 1,1"), 5);
+        let mut iter = ArrangementIter::new(springs.as_bytes(), &groups);
+        assert_eq!(iter.next().unwrap().len(), springs.len());
+        assert_eq!(iter.take(1000).count(), 1000);
+    }
+
+    #[test]
+    fn test_sampler() {
         let input = example_parsed!();
-        assert_eq!(solve_part2(&input), 24);
+        let row = &input.rows[5];
+        let all: Vec<String> = row.arrangements().collect();
+        let mut sampler = row.sampler(7).unwrap();
+        let mut seen = vec![0; all.len()];
+        for _ in 0..10000 {
+            let sample = sampler.sample().unwrap();
+            seen[all.iter().position(|a| *a == sample).unwrap()] += 1;
+        }
+        // 10 arrangements with 1000 expected draws each
+        assert!(seen.iter().all(|&n| (850..1150).contains(&n)), "{:?}", seen);
+
+        let mut a = row.sampler(3).unwrap();
+        let mut b = row.sampler(3).unwrap();
+        assert_eq!(a.sample(), b.sample());
+        assert_eq!(parse_row("#.# 1").sampler(3).unwrap().sample(), None);
+    }
+
+    #[test]
+    fn test_long_rows() {
+        // binomial(161, 40) arrangements, more than fit in a u64
+        let line = format!("{} {}", "?".repeat(200), vec!["1"; 40].join(","));
+        let row = parse_row(&line);
+        assert_eq!(
+            arrangements(row.springs.as_bytes(), &row.groups),
+            Ok(114936463738633850439458482880400722800)
+        );
+        let groups = |arrangement: &str| -> Vec<usize> {
+            arrangement
+                .split('.')
+                .filter(|s| !s.is_empty())
+                .map(str::len)
+                .collect()
+        };
+        // preferring '.' pushes every group to the right end
+        let first = row.arrangements().next().unwrap();
+        assert_eq!(first, ".".repeat(121) + &"#.".repeat(39) + "#");
+        let mut sampler = row.sampler(5).unwrap();
+        for _ in 0..10 {
+            let sample = sampler.sample().unwrap();
+            assert_eq!(sample.len(), 200);
+            assert_eq!(groups(&sample), row.groups);
+        }
+        // binomial(226, 75) does not even fit in a u128, but the arrangements can still be listed
+        let line = format!("{} {}", "?".repeat(300), vec!["1"; 75].join(","));
+        let row = parse_row(&line);
+        assert_eq!(
+            arrangements(row.springs.as_bytes(), &row.groups),
+            Err(TooManyArrangements)
+        );
+        assert!(row.sampler(5).is_err());
+        assert_eq!(row.arrangements().take(3).count(), 3);
+    }
+
+    #[test]
+    fn test_refine_line() {
+        // the overlap of a 3 in 5 cells is always damaged
+        assert_eq!(refine_line(b"?????", &[3]), Some(b"??#??".to_vec()));
+        assert_eq!(refine_line(b"?????", &[1, 3]), Some(b"#.###".to_vec()));
+        assert_eq!(refine_line(b"??#??", &[]), None);
+        assert_eq!(refine_line(b"?.???", &[2, 1]), None);
+        assert_eq!(refine_line(b"???", &[]), Some(b"...".to_vec()));
+        // far too many arrangements to count in a usize
+        let wide = vec![b'?'; 200];
+        assert_eq!(refine_line(&wide, &[1; 40]), Some(wide.clone()));
+    }
+
+    #[test]
+    fn test_nonogram_unique() {
+        // a smiley
+        let nonogram = Nonogram::parse("1,1\n1,1\n1,1\n3\n\n1\n2,1\n1\n2,1\n1");
+        assert_eq!(
+            nonogram.rows,
+            vec![vec![1, 1], vec![1, 1], vec![1, 1], vec![3]]
+        );
+        let expected = ".#.#.\n.#.#.\n#...#\n.###.\n";
+        // line by line propagation is enough here
+        let mut grid = vec![vec![b'?'; 5]; 4];
+        assert!(nonogram.propagate(&mut grid));
+        assert_eq!(grid_to_string(&grid), expected);
+        match nonogram.solve() {
+            Solutions::Unique(grid) => assert_eq!(grid_to_string(&grid), expected),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_nonogram_needs_backtracking() {
+        // propagation alone cannot decide between the two diagonals
+        let nonogram = Nonogram::parse("1\n1\n\n1\n1");
+        match nonogram.solve() {
+            Solutions::Multiple(first, second) => {
+                assert_eq!(grid_to_string(&first), "#.\n.#\n");
+                assert_eq!(grid_to_string(&second), ".#\n#.\n");
+            }
+            other => panic!("{:?}", other),
+        }
+        // the corner rules one diagonal out after a guess
+        let nonogram = Nonogram::parse("1\n1,1\n1\n\n1\n1,1\n1");
+        match nonogram.solve() {
+            Solutions::Unique(grid) => assert_eq!(grid_to_string(&grid), ".#.\n#.#\n.#.\n"),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(Nonogram::parse("2\n\n1\n1\n1").solve(), Solutions::None);
     }
 }
@@ -1,10 +1,78 @@
-use std::str;
+// a row or column of arbitrary width, bit i is the i-th cell
+#[derive(PartialEq, Debug, Clone)]
+struct BitSet {
+    len: usize,
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    fn new(len: usize) -> BitSet {
+        BitSet {
+            len,
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    fn set(&mut self, i: usize) {
+        assert!(i < self.len, "bit {} out of {}", i, self.len);
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+
+    fn get(&self, i: usize) -> bool {
+        assert!(i < self.len, "bit {} out of {}", i, self.len);
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    // number of cells which differ, the popcount of the XOR
+    fn difference(&self, other: &BitSet) -> usize {
+        assert_eq!(self.len, other.len);
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| (a ^ b).count_ones() as usize)
+            .sum()
+    }
+
+    // the cells which differ, in order
+    fn differences(&self, other: &BitSet) -> Vec<usize> {
+        assert_eq!(self.len, other.len);
+        let mut cells = vec![];
+        for (w, (a, b)) in self.words.iter().zip(&other.words).enumerate() {
+            let mut xor = a ^ b;
+            while xor != 0 {
+                cells.push(w * 64 + xor.trailing_zeros() as usize);
+                // clear the lowest bit
+                xor &= xor - 1;
+            }
+        }
+        cells
+    }
+}
 
 #[derive(PartialEq, Debug)]
 struct Pattern {
-    // assumes, that rows and columns are all smaller than 64 characters long
-    rows: Vec<u64>,
-    columns: Vec<u64>,
+    rows: Vec<BitSet>,
+    columns: Vec<BitSet>,
+}
+
+// (row, column)
+type Cell = (usize, usize);
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Orientation {
+    // the mirror lies between two rows
+    Horizontal,
+    // the mirror lies between two columns
+    Vertical,
+}
+
+#[derive(PartialEq, Debug)]
+struct Reflection {
+    orientation: Orientation,
+    // number of rows above or columns left of the mirror
+    line: usize,
+    // each smudge with its mirror image, flipping either one of them fixes it
+    smudges: Vec<(Cell, Cell)>,
 }
 
 #[derive(PartialEq, Debug)]
@@ -13,22 +81,20 @@ struct PuzzleInput {
 }
 
 fn parse_pattern(pattern_str: &str) -> Pattern {
+    let height = pattern_str.lines().count();
+    let width = pattern_str.lines().next().unwrap().len();
     let mut rows = vec![];
-    let width = pattern_str.find('\n').unwrap();
-    let mut columns = vec![0; width];
+    let mut columns = vec![BitSet::new(height); width];
     for (i, line) in pattern_str.lines().enumerate() {
-        let mut row = 0u64;
-        for (j, c) in line.bytes().rev().enumerate() {
-            if c == b'#' {
-                row = row | (1u64 << j);
-            }
-        }
-        rows.push(row);
+        assert_eq!(line.len(), width, "row {} has a different width", i);
+        let mut row = BitSet::new(width);
         for (j, c) in line.bytes().enumerate() {
             if c == b'#' {
-                columns[j] = columns[j] | (1u64 << i);
+                row.set(j);
+                columns[j].set(i);
             }
         }
+        rows.push(row);
     }
     Pattern { rows, columns }
 }
@@ -38,78 +104,273 @@ fn parse_input(input: &str) -> PuzzleInput {
     PuzzleInput { patterns }
 }
 
-fn detect_reflection(columns: &Vec<u64>) -> Vec<usize> {
-    let n = columns.len();
+// lines before which the pattern mirrors, with exactly `smudges` cells differing from their mirror image
+fn detect_reflection_with_smudges(lines: &[BitSet], smudges: usize) -> Vec<usize> {
+    let n = lines.len();
     let mut reflections = vec![];
     for i in 1..n {
         let size = std::cmp::min(i, n - i);
-        let before = columns[i - size..i].iter().rev();
-        let after = columns[i..i + size].iter();
-        if before.eq(after) {
+        let before = lines[i - size..i].iter().rev();
+        let after = lines[i..i + size].iter();
+        let mut differences = 0;
+        for (a, b) in before.zip(after) {
+            differences += a.difference(b);
+            if differences > smudges {
+                break;
+            }
+        }
+        if differences == smudges {
             reflections.push(i);
         }
     }
     reflections
 }
 
-fn pattern_number(p: &Pattern) -> usize {
-    let horizontals = detect_reflection(&p.rows);
-    let verticals = detect_reflection(&p.columns);
-    if !horizontals.is_empty() {
-        horizontals[0] * 100
-    } else if !verticals.is_empty() {
-        verticals[0]
-    } else {
-        0
+// the mirrored pairs of lines around `line`, closest first
+fn mirrored_pairs(n: usize, line: usize) -> impl Iterator<Item = (usize, usize)> {
+    let size = std::cmp::min(line, n - line);
+    (0..size).map(move |d| (line - 1 - d, line + d))
+}
+
+// the first reflection needing exactly `smudges` flips, horizontal ones first
+fn find_reflection(p: &Pattern, smudges: usize) -> Option<Reflection> {
+    for orientation in [Orientation::Horizontal, Orientation::Vertical] {
+        let lines = match orientation {
+            Orientation::Horizontal => &p.rows,
+            Orientation::Vertical => &p.columns,
+        };
+        let Some(&line) = detect_reflection_with_smudges(lines, smudges).first() else {
+            continue;
+        };
+        let mut cells = vec![];
+        for (a, b) in mirrored_pairs(lines.len(), line) {
+            for cell in lines[a].differences(&lines[b]) {
+                cells.push(match orientation {
+                    Orientation::Horizontal => ((a, cell), (b, cell)),
+                    Orientation::Vertical => ((cell, a), (cell, b)),
+                });
+            }
+        }
+        cells.sort();
+        return Some(Reflection {
+            orientation,
+            line,
+            smudges: cells,
+        });
+    }
+    None
+}
+
+impl Reflection {
+    fn summary(&self) -> usize {
+        match self.orientation {
+            Orientation::Horizontal => self.line * 100,
+            Orientation::Vertical => self.line,
+        }
     }
 }
 
+impl std::fmt::Display for Reflection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.orientation {
+            Orientation::Horizontal => write!(f, "horizontal mirror below row {}", self.line)?,
+            Orientation::Vertical => write!(f, "vertical mirror right of column {}", self.line)?,
+        }
+        for ((row, column), (mirror_row, mirror_column)) in &self.smudges {
+            // 1-based like the puzzle
+            write!(
+                f,
+                ", smudge at row {} column {} or row {} column {}",
+                row + 1,
+                column + 1,
+                mirror_row + 1,
+                mirror_column + 1
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// 0 if the pattern has no such reflection
+fn pattern_number(p: &Pattern, smudges: usize) -> usize {
+    find_reflection(p, smudges).map_or(0, |r| r.summary())
+}
+
 fn solve_part1(input: &PuzzleInput) -> usize {
-    input.patterns.iter().map(pattern_number).sum()
+    input.patterns.iter().map(|p| pattern_number(p, 0)).sum()
 }
 
 fn solve_part2(input: &PuzzleInput) -> usize {
-    input
-        .patterns
-        .iter()
-        .map(|p| {
-            let original = pattern_number(p);
-            // crazy inefficient solution
-            // try out every possibility
-            for ri in 0..p.rows.len() {
-                for ci in 0..p.columns.len() {
-                    let mut rows = p.rows.clone();
-                    let mut columns = p.columns.clone();
-                    rows[ri] = rows[ri] ^ (1u64 << (p.columns.len() - 1 - ci));
-                    columns[ci] = columns[ci] ^ (1u64 << ri);
-
-                    let nums: Vec<usize> = {
-                        let verticals = detect_reflection(&columns);
-                        let horizontals = detect_reflection(&rows);
-                        horizontals
-                            .iter()
-                            .map(|hi| hi * 100)
-                            .chain(verticals.iter().map(|vi| *vi))
-                            .collect()
-                    };
-                    let nums: Vec<&usize> =
-                        nums.iter().filter(|&a| *a != original && *a != 0).collect();
-                    assert!(nums.len() < 2);
+    // the smudge is the one cell differing across the new line, the old line has none
+    input.patterns.iter().map(|p| pattern_number(p, 1)).sum()
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Symmetry {
+    // rotation by 180°
+    HalfTurn,
+    // rotation by 90°, square regions only
+    QuarterTurn,
+    // mirror along the top left to bottom right diagonal, square regions only
+    Transpose,
+    // mirror along the top right to bottom left diagonal, square regions only
+    AntiTranspose,
+}
 
-                    if nums.len() == 1 {
-                        return *nums[0];
+impl Symmetry {
+    // where the cell goes within a region of the given size
+    fn map(self, (row, column): Cell, (height, width): (usize, usize)) -> Cell {
+        match self {
+            Symmetry::HalfTurn => (height - 1 - row, width - 1 - column),
+            Symmetry::QuarterTurn => (column, height - 1 - row),
+            Symmetry::Transpose => (column, row),
+            Symmetry::AntiTranspose => (width - 1 - column, height - 1 - row),
+        }
+    }
+
+    fn needs_square(self) -> bool {
+        self != Symmetry::HalfTurn
+    }
+}
+
+impl Pattern {
+    fn cell(&self, (row, column): Cell) -> bool {
+        self.rows[row].get(column)
+    }
+
+    fn size(&self) -> (usize, usize) {
+        (self.rows.len(), self.columns.len())
+    }
+
+    // flips needed to make the region starting at `top_left` symmetric
+    // the cells a symmetry maps onto each other form an orbit, the minority in each orbit has to flip
+    fn symmetry_flips(&self, symmetry: Symmetry, top_left: Cell, size: (usize, usize)) -> usize {
+        assert!(
+            !symmetry.needs_square() || size.0 == size.1,
+            "{:?} needs a square",
+            symmetry
+        );
+        let (top, left) = top_left;
+        let mut flips = 0;
+        for row in 0..size.0 {
+            for column in 0..size.1 {
+                let mut orbit = vec![(row, column)];
+                loop {
+                    let next = symmetry.map(*orbit.last().unwrap(), size);
+                    if next == orbit[0] {
+                        break;
                     }
+                    orbit.push(next);
+                }
+                // every orbit is counted once, from its smallest cell
+                if orbit.iter().min() != Some(&(row, column)) {
+                    continue;
                 }
+                let damaged = orbit
+                    .iter()
+                    .filter(|&&(r, c)| self.cell((top + r, left + c)))
+                    .count();
+                flips += damaged.min(orbit.len() - damaged);
             }
-            panic!("No reflections after removing smudges");
-        })
-        .sum()
+        }
+        flips
+    }
+
+    // flips needed for the whole pattern, None if that is more than `tolerance` or the pattern is not square
+    fn symmetry(&self, symmetry: Symmetry, tolerance: usize) -> Option<usize> {
+        let (height, width) = self.size();
+        if symmetry.needs_square() && height != width {
+            return None;
+        }
+        let flips = self.symmetry_flips(symmetry, (0, 0), (height, width));
+        (flips <= tolerance).then_some(flips)
+    }
+
+    // top left corners of the size×size squares which are symmetric within `tolerance`, with their flips
+    fn symmetric_squares(
+        &self,
+        symmetry: Symmetry,
+        size: usize,
+        tolerance: usize,
+    ) -> Vec<(Cell, usize)> {
+        let (height, width) = self.size();
+        let mut squares = vec![];
+        for top in 0..=height.saturating_sub(size) {
+            for left in 0..=width.saturating_sub(size) {
+                if top + size > height || left + size > width {
+                    continue;
+                }
+                let flips = self.symmetry_flips(symmetry, (top, left), (size, size));
+                if flips <= tolerance {
+                    squares.push(((top, left), flips));
+                }
+            }
+        }
+        squares
+    }
 }
 
 fn main() {
     let input = include_str!("../input");
     let input = parse_input(input);
 
+    // `reflections <k>` lists the reflection of every pattern needing k flips
+    // `symmetries <tolerance>` lists the other symmetries and the largest quarter turn square
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("reflections") => {
+            let Some(smudges) = args.get(2).and_then(|k| k.parse::<usize>().ok()) else {
+                eprintln!("usage: day13 reflections <k>");
+                return;
+            };
+            for (i, pattern) in input.patterns.iter().enumerate() {
+                match find_reflection(pattern, smudges) {
+                    Some(reflection) => println!("pattern {}: {}", i + 1, reflection),
+                    None => println!("pattern {}: no reflection", i + 1),
+                }
+            }
+            return;
+        }
+        Some("symmetries") => {
+            let Some(tolerance) = args.get(2).and_then(|t| t.parse::<usize>().ok()) else {
+                eprintln!("usage: day13 symmetries <tolerance>");
+                return;
+            };
+            for (i, pattern) in input.patterns.iter().enumerate() {
+                print!("pattern {}:", i + 1);
+                for symmetry in [
+                    Symmetry::HalfTurn,
+                    Symmetry::Transpose,
+                    Symmetry::AntiTranspose,
+                ] {
+                    if let Some(flips) = pattern.symmetry(symmetry, tolerance) {
+                        print!(" {:?} ({} flips)", symmetry, flips);
+                    }
+                }
+                let (height, width) = pattern.size();
+                // squares of size 1 are always symmetric
+                let largest = (2..=height.min(width)).rev().find_map(|size| {
+                    let squares = pattern.symmetric_squares(Symmetry::QuarterTurn, size, tolerance);
+                    squares
+                        .first()
+                        .map(|&(corner, flips)| (size, corner, flips))
+                });
+                if let Some((size, (top, left), flips)) = largest {
+                    print!(
+                        " QuarterTurn {0}x{0} at row {1} column {2} ({3} flips)",
+                        size,
+                        top + 1,
+                        left + 1,
+                        flips
+                    );
+                }
+                println!();
+            }
+            return;
+        }
+        _ => {}
+    }
+
     println!("Part 1: {}", solve_part1(&input));
     println!("Part 2: {}", solve_part2(&input));
 }
@@ -120,7 +381,22 @@ mod tests {
 
     use super::*;
 
-    const EXAMPLE: &'static str = "#.##..##.
+    // cells from first to last, '1' for '#'
+    fn bits(cells: &str) -> BitSet {
+        let mut set = BitSet::new(cells.len());
+        for (i, c) in cells.bytes().enumerate() {
+            if c == b'1' {
+                set.set(i);
+            }
+        }
+        set
+    }
+
+    fn detect_reflection(lines: &[BitSet]) -> Vec<usize> {
+        detect_reflection_with_smudges(lines, 0)
+    }
+
+    const EXAMPLE: &str = "#.##..##.
 ..#.##.#.
 ##......#
 ##......#
@@ -146,7 +422,7 @@ mod tests {
 ..#..##..#..#
 .#........#.#";
 
-    const EXAMPLE2: &'static str = "#.##....##.#.
+    const EXAMPLE2: &str = "#.##....##.#.
 #.##....##.#.
 .#.#....#.#.#
 .###....###..
@@ -172,60 +448,74 @@ mod tests {
                 patterns: vec![
                     Pattern {
                         rows: vec![
-                            0b101100110,
-                            0b001011010,
-                            0b110000001,
-                            0b110000001,
-                            0b001011010,
-                            0b001100110,
-                            0b101011010,
+                            bits("101100110"),
+                            bits("001011010"),
+                            bits("110000001"),
+                            bits("110000001"),
+                            bits("001011010"),
+                            bits("001100110"),
+                            bits("101011010"),
                         ],
                         columns: vec![
-                            0b1001101, 0b0001100, 0b1110011, 0b0100001, 0b1010010, 0b1010010,
-                            0b0100001, 0b1110011, 0b0001100,
+                            bits("1011001"),
+                            bits("0011000"),
+                            bits("1100111"),
+                            bits("1000010"),
+                            bits("0100101"),
+                            bits("0100101"),
+                            bits("1000010"),
+                            bits("1100111"),
+                            bits("0011000"),
                         ],
                     },
                     Pattern {
                         rows: vec![
-                            0b100011001,
-                            0b100001001,
-                            0b001100111,
-                            0b111110110,
-                            0b111110110,
-                            0b001100111,
-                            0b100001001,
+                            bits("100011001"),
+                            bits("100001001"),
+                            bits("001100111"),
+                            bits("111110110"),
+                            bits("111110110"),
+                            bits("001100111"),
+                            bits("100001001"),
                         ],
                         columns: vec![
-                            0b1011011, 0b0011000, 0b0111100, 0b0111100, 0b0011001, 0b1000011,
-                            0b0111100, 0b0111100, 0b1100111,
+                            bits("1101101"),
+                            bits("0001100"),
+                            bits("0011110"),
+                            bits("0011110"),
+                            bits("1001100"),
+                            bits("1100001"),
+                            bits("0011110"),
+                            bits("0011110"),
+                            bits("1110011"),
                         ],
                     },
                     Pattern {
                         rows: vec![
-                            0b1011000011010,
-                            0b1011000011010,
-                            0b0101000010101,
-                            0b0111000011100,
-                            0b1010111101010,
-                            0b0111100011101,
-                            0b1001111110010,
-                            0b0010011001001,
-                            0b0100000000101,
+                            bits("1011000011010"),
+                            bits("1011000011010"),
+                            bits("0101000010101"),
+                            bits("0111000011100"),
+                            bits("1010111101010"),
+                            bits("0111100011101"),
+                            bits("1001111110010"),
+                            bits("0010011001001"),
+                            bits("0100000000101"),
                         ],
                         columns: vec![
-                            0b001010011,
-                            0b100101100,
-                            0b010111011,
-                            0b001101111,
-                            0b001110000,
-                            0b011010000,
-                            0b011010000,
-                            0b001010000,
-                            0b001101111,
-                            0b010111011,
-                            0b100101100,
-                            0b001010011,
-                            0b110100100,
+                            bits("110010100"),
+                            bits("001101001"),
+                            bits("110111010"),
+                            bits("111101100"),
+                            bits("000011100"),
+                            bits("000010110"),
+                            bits("000010110"),
+                            bits("000010100"),
+                            bits("111101100"),
+                            bits("110111010"),
+                            bits("001101001"),
+                            bits("110010100"),
+                            bits("001001011"),
                         ],
                     },
                 ],
@@ -236,7 +526,7 @@ mod tests {
     #[test]
     fn test_parsing() {
         let input = example_parsed!();
-        assert_eq!(parse_input(&EXAMPLE), input);
+        assert_eq!(parse_input(EXAMPLE), input);
     }
 
     #[test]
@@ -260,7 +550,7 @@ mod tests {
 
     #[test]
     fn test_special_case() {
-        let input = parse_input(&EXAMPLE2);
+        let input = parse_input(EXAMPLE2);
         assert_eq!(detect_reflection(&input.patterns[0].rows), vec![1]);
         assert_eq!(detect_reflection(&input.patterns[0].columns), vec![6]);
 
@@ -273,4 +563,190 @@ mod tests {
         let input = example_parsed!();
         assert_eq!(solve_part2(&input), 406);
     }
+
+    #[test]
+    fn test_bitset() {
+        let mut a = BitSet::new(130);
+        let mut b = BitSet::new(130);
+        assert_eq!(a.words.len(), 3);
+        a.set(3);
+        a.set(129);
+        b.set(3);
+        assert_eq!(a.difference(&b), 1);
+        b.set(64);
+        assert_eq!(a.difference(&b), 2);
+        assert_eq!(bits("0101"), {
+            let mut c = BitSet::new(4);
+            c.set(1);
+            c.set(3);
+            c
+        });
+    }
+
+    #[test]
+    fn test_wide_pattern() {
+        // 100 columns mirrored before column 70, column j holds the bits of its distance to the edge
+        // and their parity, so any other two columns differ in at least two cells
+        let pattern = |smudge: Option<(usize, usize)>| -> Pattern {
+            let rows: Vec<String> = (0..8)
+                .map(|b| {
+                    (0..100)
+                        .map(|j: usize| {
+                            let mirrored = if j < 70 { j } else { 139 - j };
+                            let damaged = if b < 7 {
+                                (mirrored >> b) & 1 == 1
+                            } else {
+                                mirrored.count_ones() % 2 == 1
+                            };
+                            if damaged != (smudge == Some((b, j))) {
+                                '#'
+                            } else {
+                                '.'
+                            }
+                        })
+                        .collect()
+                })
+                .collect();
+            parse_pattern(&rows.join("\n"))
+        };
+        let clean = pattern(None);
+        assert_eq!(detect_reflection(&clean.columns), vec![70]);
+        assert_eq!(detect_reflection(&clean.rows), vec![]);
+        assert_eq!(pattern_number(&clean, 0), 70);
+        assert_eq!(pattern_number(&clean, 1), 0);
+        let smudged = pattern(Some((2, 99)));
+        assert_eq!(detect_reflection(&smudged.columns), vec![]);
+        assert_eq!(
+            detect_reflection_with_smudges(&smudged.columns, 1),
+            vec![70]
+        );
+        assert_eq!(pattern_number(&smudged, 1), 70);
+    }
+
+    #[test]
+    fn test_differences() {
+        let a = bits(&"1".repeat(70));
+        let mut b = a.clone();
+        b.words[0] ^= 1 << 5;
+        b.words[1] ^= 1 << 2;
+        assert_eq!(a.differences(&b), vec![5, 66]);
+        assert_eq!(a.differences(&a), vec![]);
+    }
+
+    #[test]
+    fn test_find_reflection() {
+        let input = example_parsed!();
+        assert_eq!(
+            find_reflection(&input.patterns[0], 0),
+            Some(Reflection {
+                orientation: Orientation::Vertical,
+                line: 5,
+                smudges: vec![],
+            })
+        );
+        // the smudges from the puzzle description, at the top left and in row 2 column 5 (1-based)
+        assert_eq!(
+            find_reflection(&input.patterns[0], 1),
+            Some(Reflection {
+                orientation: Orientation::Horizontal,
+                line: 3,
+                smudges: vec![((0, 0), (5, 0))],
+            })
+        );
+        assert_eq!(
+            find_reflection(&input.patterns[1], 1),
+            Some(Reflection {
+                orientation: Orientation::Horizontal,
+                line: 1,
+                smudges: vec![((0, 4), (1, 4))],
+            })
+        );
+        assert_eq!(
+            find_reflection(&input.patterns[1], 1).unwrap().to_string(),
+            "horizontal mirror below row 1, smudge at row 1 column 5 or row 2 column 5"
+        );
+        assert_eq!(
+            find_reflection(&input.patterns[2], 0).unwrap().summary(),
+            100
+        );
+    }
+
+    #[test]
+    fn test_find_reflection_k_smudges() {
+        let input = example_parsed!();
+        for (i, pattern_str) in EXAMPLE.split("\n\n").enumerate() {
+            let grid: Vec<&[u8]> = pattern_str.lines().map(str::as_bytes).collect();
+            for k in 0..4 {
+                let Some(reflection) = find_reflection(&input.patterns[i], k) else {
+                    continue;
+                };
+                assert_eq!(reflection.smudges.len(), k);
+                for &((row, column), (mirror_row, mirror_column)) in &reflection.smudges {
+                    assert_ne!(grid[row][column], grid[mirror_row][mirror_column]);
+                    let (a, b) = match reflection.orientation {
+                        Orientation::Horizontal => (row, mirror_row),
+                        Orientation::Vertical => (column, mirror_column),
+                    };
+                    assert_eq!(a + b + 1, 2 * reflection.line);
+                }
+            }
+        }
+        // no panic when nothing mirrors, a 3x3 pattern needs 4 flips at most
+        let pattern = parse_pattern("#..\n...\n..#");
+        assert_eq!(find_reflection(&pattern, 9), None);
+        assert_eq!(pattern_number(&pattern, 9), 0);
+    }
+
+    #[test]
+    fn test_half_turn() {
+        let pattern = parse_pattern("#..\n.#.\n..#\n#.#");
+        assert_eq!(pattern.symmetry(Symmetry::HalfTurn, 0), None);
+        // only the first row matches the last one turned around
+        assert_eq!(pattern.symmetry(Symmetry::HalfTurn, 5), Some(3));
+        let pattern = parse_pattern("##.\n.#.\n.##");
+        assert_eq!(pattern.symmetry(Symmetry::HalfTurn, 0), Some(0));
+        let input = example_parsed!();
+        assert_eq!(input.patterns[0].symmetry(Symmetry::Transpose, 100), None);
+    }
+
+    #[test]
+    fn test_diagonals() {
+        let pattern = parse_pattern("#.#\n.##\n##.");
+        assert_eq!(pattern.symmetry(Symmetry::Transpose, 0), Some(0));
+        assert_eq!(pattern.symmetry(Symmetry::AntiTranspose, 0), None);
+        // every pair off the anti-diagonal differs
+        assert_eq!(pattern.symmetry(Symmetry::AntiTranspose, 3), Some(3));
+        let pattern = parse_pattern(".##\n..#\n...");
+        assert_eq!(pattern.symmetry(Symmetry::AntiTranspose, 0), Some(0));
+        // all '#' lie above the diagonal, each needs a partner below it
+        assert_eq!(pattern.symmetry(Symmetry::Transpose, 2), None);
+        assert_eq!(pattern.symmetry(Symmetry::Transpose, 3), Some(3));
+    }
+
+    #[test]
+    fn test_quarter_turn_squares() {
+        let pattern = parse_pattern("......\n.#.#..\n......\n.#.#..\n....#.");
+        // the four '#' around the empty centre of rows 2 to 4
+        let squares = pattern.symmetric_squares(Symmetry::QuarterTurn, 3, 0);
+        assert!(squares.contains(&((1, 1), 0)));
+        assert!(!squares.contains(&((2, 2), 0)));
+        assert_eq!(
+            pattern.symmetric_squares(Symmetry::QuarterTurn, 5, 0),
+            vec![]
+        );
+        // the '#' in the bottom row is the only odd one out of the 5x5 square
+        assert_eq!(
+            pattern.symmetric_squares(Symmetry::QuarterTurn, 5, 1),
+            vec![((0, 0), 1)]
+        );
+        assert_eq!(
+            pattern.symmetric_squares(Symmetry::QuarterTurn, 7, 100),
+            vec![]
+        );
+        // a quarter turn symmetric square is half turn symmetric as well
+        assert_eq!(
+            pattern.symmetry_flips(Symmetry::HalfTurn, (1, 1), (3, 3)),
+            0
+        );
+    }
 }
@@ -1,7 +1,8 @@
-use std::{
-    collections::{BTreeMap},
-    fmt, str,
-};
+use std::{fmt, str};
+
+// only the hash is needed here, the module is public so the unused generator is not dead code
+#[path = "../../common/rng.rs"]
+pub mod rng;
 
 #[derive(PartialEq, Debug, Clone, PartialOrd, Eq, Ord)]
 enum Tile {
@@ -40,13 +41,6 @@ impl Platform {
             Some(p.1 as usize * self.width + p.0 as usize)
         }
     }
-    fn index2coord(&self, index: usize) -> Option<(i32, i32)> {
-        if index >= self.width * self.height {
-            None
-        } else {
-            Some(((index % self.width) as i32, (index / self.width) as i32))
-        }
-    }
 }
 
 impl fmt::Display for Platform {
@@ -63,7 +57,7 @@ impl fmt::Display for Platform {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Direction {
     North,
     South,
@@ -92,164 +86,471 @@ fn parse_input(input: &str) -> PuzzleInput {
     }
 }
 
-fn tilt_platform(platform: &Platform, direction: Direction) -> Platform {
-    let mut platform = platform.clone();
-    let (origins, offset) = match direction {
-        Direction::North => {
-            let origins: Vec<(i32, i32)> = (0..platform.width).map(|x| (x as i32, 0)).collect();
-            (origins, (0, 1))
-        }
-        Direction::South => {
-            let origins: Vec<(i32, i32)> = (0..platform.width)
-                .map(|x| (x as i32, platform.height as i32 - 1))
-                .collect();
-            (origins, (0, -1))
-        }
-        Direction::West => {
-            let origins: Vec<(i32, i32)> = (0..platform.height).map(|y| (0, y as i32)).collect();
-            (origins, (1, 0))
-        }
-        Direction::East => {
-            let origins: Vec<(i32, i32)> = (0..platform.height)
-                .map(|y| (platform.width as i32 - 1, y as i32))
-                .collect();
-            (origins, (-1, 0))
-        }
-    };
-    let next = |p: (i32, i32)| (p.0 + offset.0, p.1 + offset.1);
-    let swap = |tiles: &mut Box<[Tile]>, i1: usize, i2: usize| {
-        let t1 = tiles[i1].clone();
-        tiles[i1] = tiles[i2].clone();
-        tiles[i2] = t1.clone();
-    };
-    let mut tiles = platform.tiles.clone();
-    for origin in origins {
-        let mut cursor = origin;
-        let mut empty_space = None;
-        while let Some(i) = platform.coord2index(cursor) {
-            //dbg!(cursor, i);
-            match tiles[i] {
-                O => {
-                    if let Some(ei) = empty_space {
-                        swap(&mut tiles, i, ei);
-                        let next_empty_index = platform
-                            .coord2index(next(platform.index2coord(ei).unwrap()))
-                            .unwrap();
-                        empty_space = Some(next_empty_index);
+// a run of tiles between cube rocks or edges, from `start` in steps of `stride`
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    start: usize,
+    len: usize,
+    stride: usize,
+}
+
+impl Segment {
+    fn cell(&self, k: usize) -> usize {
+        self.start + k * self.stride
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Axis {
+    Columns,
+    Rows,
+}
+
+// after a tilt every round rock lies packed against one end of its segment,
+// so the number of rocks per segment is all there is to know
+#[derive(Debug, Clone, PartialEq)]
+struct Packing {
+    axis: Axis,
+    at_start: bool,
+    counts: Vec<u32>,
+}
+
+// the segments of every column or of every row
+struct Lines {
+    segments: Vec<Segment>,
+    // the segment each tile belongs to, NONE for cube rocks
+    segment_of: Vec<u32>,
+    // where each segment's tiles begin in `crossing`
+    offsets: Vec<usize>,
+    // for the tiles of all segments in order, the segment of the other axis they lie in
+    // so rolling rocks over to the other axis reads memory in order
+    crossing: Vec<u32>,
+}
+
+const NONE: u32 = u32::MAX;
+
+impl Lines {
+    // splits the `count` lines of `len` tiles at the cube rocks, line j starts at j * line_stride
+    fn new(
+        platform: &Platform,
+        count: usize,
+        len: usize,
+        line_stride: usize,
+        stride: usize,
+    ) -> Lines {
+        let mut segments = vec![];
+        let mut segment_of = vec![NONE; platform.tiles.len()];
+        for j in 0..count {
+            let first = j * line_stride;
+            let mut start = None;
+            for k in 0..=len {
+                let i = first + k * stride;
+                if k == len || platform.tiles[i] == C {
+                    if let Some(start) = start.take() {
+                        segments.push(Segment {
+                            start,
+                            len: (i - start) / stride,
+                            stride,
+                        });
                     }
-                }
-                C => {
-                    empty_space = None;
-                }
-                E => {
-                    if empty_space.is_none() {
-                        empty_space = Some(i)
+                } else {
+                    if start.is_none() {
+                        start = Some(i);
                     }
+                    segment_of[i] = segments.len() as u32;
                 }
             }
-            cursor = next(cursor);
+        }
+        let mut offsets = vec![];
+        let mut total = 0;
+        for segment in &segments {
+            offsets.push(total);
+            total += segment.len;
+        }
+        Lines {
+            segments,
+            segment_of,
+            offsets,
+            crossing: vec![],
+        }
+    }
+
+    fn link(&mut self, other: &Lines) {
+        self.crossing = self
+            .segments
+            .iter()
+            .flat_map(|segment| (0..segment.len).map(|k| other.segment_of[segment.cell(k)]))
+            .collect();
+    }
+
+    // the positions within segment `s` holding rocks
+    fn packed(&self, packing: &Packing, s: usize) -> std::ops::Range<usize> {
+        let count = packing.counts[s] as usize;
+        if packing.at_start {
+            0..count
+        } else {
+            self.segments[s].len - count..self.segments[s].len
         }
     }
-    platform.tiles = tiles;
-    platform
 }
 
-fn platform_load(platform: &Platform, direction: Direction) -> usize {
-    let (origins, offset) = match direction {
-        Direction::North => {
-            let origins: Vec<(i32, i32)> = (0..platform.width)
-                .map(|x| (x as i32, platform.height as i32 - 1))
-                .collect();
-            (origins, (0, -1))
-        }
-        Direction::South => {
-            let origins: Vec<(i32, i32)> = (0..platform.width).map(|x| (x as i32, 0)).collect();
-            (origins, (0, 1))
-        }
-        Direction::West => {
-            let origins: Vec<(i32, i32)> = (0..platform.height)
-                .map(|y| (platform.width as i32 - 1, y as i32))
-                .collect();
-            (origins, (-1, 0))
-        }
-        Direction::East => {
-            let origins: Vec<(i32, i32)> = (0..platform.height).map(|y| (0, y as i32)).collect();
-            (origins, (1, 0))
-        }
-    };
-    let next = |p: (i32, i32)| (p.0 + offset.0, p.1 + offset.1);
-    let mut total_load = 0;
-    for origin in origins {
-        let mut cursor = origin;
-        let mut load = 1;
-        while let Some(i) = platform.coord2index(cursor) {
-            if platform.tiles[i] == O {
-                total_load += load;
+// cube rocks never move, so the segments the round rocks roll in are found once per platform
+struct Tilter {
+    columns: Lines,
+    rows: Lines,
+}
+
+impl Tilter {
+    fn new(platform: &Platform) -> Tilter {
+        let (width, height) = (platform.width, platform.height);
+        let mut columns = Lines::new(platform, width, height, 1, width);
+        let mut rows = Lines::new(platform, height, width, width, 1);
+        columns.link(&rows);
+        rows.link(&columns);
+        Tilter { columns, rows }
+    }
+
+    fn lines(&self, axis: Axis) -> &Lines {
+        match axis {
+            Axis::Columns => &self.columns,
+            Axis::Rows => &self.rows,
+        }
+    }
+
+    // the segments a tilt rolls along and whether the rocks end up at their start
+    fn target(direction: Direction) -> (Axis, bool) {
+        match direction {
+            Direction::North => (Axis::Columns, true),
+            Direction::South => (Axis::Columns, false),
+            Direction::West => (Axis::Rows, true),
+            Direction::East => (Axis::Rows, false),
+        }
+    }
+
+    // tilts a platform whose rocks may lie anywhere
+    fn pack(&self, platform: &Platform, direction: Direction) -> Packing {
+        let (axis, at_start) = Tilter::target(direction);
+        let lines = self.lines(axis);
+        let mut counts = vec![0; lines.segments.len()];
+        for (i, t) in platform.tiles.iter().enumerate() {
+            if *t == O {
+                counts[lines.segment_of[i] as usize] += 1;
             }
-            cursor = next(cursor);
-            load += 1;
+        }
+        Packing {
+            axis,
+            at_start,
+            counts,
         }
     }
-    total_load
+
+    // tilts packed rocks, each rock is touched once rather than every tile
+    fn tilt_packing(&self, packing: &Packing, direction: Direction) -> Packing {
+        let (axis, at_start) = Tilter::target(direction);
+        if axis == packing.axis {
+            // the rocks just slide to the other end of their segment
+            return Packing {
+                axis,
+                at_start,
+                counts: packing.counts.clone(),
+            };
+        }
+        let from = self.lines(packing.axis);
+        let mut counts = vec![0; self.lines(axis).segments.len()];
+        for (s, offset) in from.offsets.iter().enumerate() {
+            for k in from.packed(packing, s) {
+                counts[from.crossing[offset + k] as usize] += 1;
+            }
+        }
+        Packing {
+            axis,
+            at_start,
+            counts,
+        }
+    }
+
+    fn unpack(&self, packing: &Packing, platform: &mut Platform) {
+        for t in platform.tiles.iter_mut() {
+            if *t == O {
+                *t = E;
+            }
+        }
+        let lines = self.lines(packing.axis);
+        for (s, segment) in lines.segments.iter().enumerate() {
+            for k in lines.packed(packing, s) {
+                platform.tiles[segment.cell(k)] = O;
+            }
+        }
+    }
+
+    fn tilt(&self, platform: &mut Platform, direction: Direction) {
+        let packing = self.pack(platform, direction);
+        self.unpack(&packing, platform);
+    }
+
+    // brings `to`, tilted from the previous version of `from`, up to date with the changes to `from`
+    // only the rocks which moved are rolled over, returns the changes to `to`
+    fn apply_changes(&self, from: &Packing, changes: &Changes, to: &mut Packing) -> Changes {
+        if from.axis == to.axis {
+            for &(s, _) in changes {
+                to.counts[s as usize] = from.counts[s as usize];
+            }
+            return changes.clone();
+        }
+        let lines = self.lines(from.axis);
+        let mut deltas: Vec<(u32, i32)> = vec![];
+        for &(s, old) in changes {
+            let new = from.counts[s as usize];
+            let len = lines.segments[s as usize].len as u32;
+            let (low, high) = (old.min(new), old.max(new));
+            let moved = if from.at_start {
+                low..high
+            } else {
+                len - high..len - low
+            };
+            let delta = if new > old { 1 } else { -1 };
+            for k in moved {
+                deltas.push((
+                    lines.crossing[lines.offsets[s as usize] + k as usize],
+                    delta,
+                ));
+            }
+        }
+        deltas.sort_unstable_by_key(|&(s, _)| s);
+        let mut changed = vec![];
+        for group in deltas.chunk_by(|a, b| a.0 == b.0) {
+            let s = group[0].0;
+            let delta: i32 = group.iter().map(|&(_, delta)| delta).sum();
+            if delta != 0 {
+                let old = to.counts[s as usize];
+                to.counts[s as usize] = (old as i32 + delta) as u32;
+                changed.push((s, old));
+            }
+        }
+        changed
+    }
 }
 
-fn solve_part1(platform: &Platform) -> usize {
-    let tilted = tilt_platform(platform, Direction::North);
-    platform_load(&tilted, Direction::North)
+// segments whose rock count changed, with the count before
+type Changes = Vec<(u32, u32)>;
+
+const CYCLE: [Direction; 4] = [
+    Direction::North,
+    Direction::West,
+    Direction::South,
+    Direction::East,
+];
+
+// splitmix64 of one segment's count, the state hash is the sum over all segments
+// so a change to one count updates it without looking at the others
+fn count_hash(s: u32, count: u32) -> u64 {
+    rng::mix((s as u64) << 32 | count as u64)
+}
+
+fn state_hash(packing: &Packing) -> u64 {
+    packing
+        .counts
+        .iter()
+        .enumerate()
+        .fold(0, |hash, (s, &count)| {
+            hash.wrapping_add(count_hash(s as u32, count))
+        })
 }
 
-fn cycle(platform: &Platform) -> Platform {
-    let platform = tilt_platform(platform, Direction::North);
-    let platform = tilt_platform(&platform, Direction::West);
-    let platform = tilt_platform(&platform, Direction::South);
-    
-    tilt_platform(&platform, Direction::East)
+fn diff(old: &Packing, new: &Packing) -> Changes {
+    old.counts
+        .iter()
+        .zip(&new.counts)
+        .enumerate()
+        .filter(|(_, (old, new))| old != new)
+        .map(|(s, (&old, _))| (s as u32, old))
+        .collect()
 }
 
-fn solve_part2(platform: &Platform) -> usize {
-    let mut last_cycle_platform = platform.clone();
-    let mut store: BTreeMap<Box<[Tile]>, Box<[Tile]>> = BTreeMap::new();
-    let n = 1000000000;
-    let mut i = 0;
-    while i < n {
-        let stored_platform = store.get(&last_cycle_platform.tiles);
-        let platform = if let Some(previous) = stored_platform {
-            // it repeats!
-            let mut j = 1;
-            let mut tiles = previous;
-            while tiles != &last_cycle_platform.tiles {
-                tiles = store.get(tiles).unwrap();
-                j += 1;
+// the packings after every tilt of the latest round, each round only rolls the rocks
+// which moved in the round before, which gets cheap once the platform settles
+struct Spinner<'a> {
+    tilter: &'a Tilter,
+    directions: &'a [Direction],
+    packings: Vec<Packing>,
+    // changes made by the last tilt, None after the first round as there is nothing to compare with
+    changes: Option<Changes>,
+    // of the last packing
+    hash: u64,
+}
+
+impl<'a> Spinner<'a> {
+    // runs the first round, the rocks may lie anywhere before it
+    fn new(tilter: &'a Tilter, directions: &'a [Direction], platform: &Platform) -> Spinner<'a> {
+        let mut packings = vec![tilter.pack(platform, directions[0])];
+        for &direction in &directions[1..] {
+            packings.push(tilter.tilt_packing(packings.last().unwrap(), direction));
+        }
+        let hash = state_hash(packings.last().unwrap());
+        Spinner {
+            tilter,
+            directions,
+            packings,
+            changes: None,
+            hash,
+        }
+    }
+
+    fn state(&self) -> &Packing {
+        self.packings.last().unwrap()
+    }
+
+    fn round(&mut self) {
+        let last = self.packings.len() - 1;
+        let mut changes = match self.changes.take() {
+            // tilting the same way again moves nothing
+            Some(_) if last == 0 => vec![],
+            Some(changes) => {
+                let (first, rest) = self.packings.split_at_mut(last);
+                self.tilter.apply_changes(&rest[0], &changes, &mut first[0])
             }
-            println!("Repeates after {j} cycles");
-            let iterations_to_skip = (n - i) / j * j;
-            i += iterations_to_skip;
-            Platform {
-                tiles: previous.clone(),
-                width: last_cycle_platform.width,
-                height: last_cycle_platform.height,
+            None => {
+                let first = self
+                    .tilter
+                    .tilt_packing(&self.packings[last], self.directions[0]);
+                let changes = diff(&self.packings[0], &first);
+                self.packings[0] = first;
+                changes
             }
-        } else {
-            let p = cycle(&last_cycle_platform);
-            store.insert(last_cycle_platform.tiles.clone(), p.tiles.clone());
-            p
         };
+        for j in 1..self.packings.len() {
+            let (before, after) = self.packings.split_at_mut(j);
+            changes = self
+                .tilter
+                .apply_changes(&before[j - 1], &changes, &mut after[0]);
+        }
+        for &(s, old) in &changes {
+            let new = self.packings[last].counts[s as usize];
+            self.hash = self
+                .hash
+                .wrapping_sub(count_hash(s, old))
+                .wrapping_add(count_hash(s, new));
+        }
+        self.changes = Some(changes);
+    }
+}
 
-        if last_cycle_platform == platform {
-            last_cycle_platform = platform;
+fn tilt_platform(platform: &Platform, direction: Direction) -> Platform {
+    let mut platform = platform.clone();
+    Tilter::new(&platform).tilt(&mut platform, direction);
+    platform
+}
+
+// the load on the support beams at the `direction` edge, a rock weighs its distance
+// from the opposite edge counting its own row or column
+fn platform_load(platform: &Platform, direction: Direction) -> usize {
+    let (width, height) = (platform.width, platform.height);
+    platform
+        .tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| **t == O)
+        .map(|(i, _)| {
+            let (x, y) = (i % width, i / width);
+            match direction {
+                Direction::North => height - y,
+                Direction::South => y + 1,
+                Direction::West => width - x,
+                Direction::East => x + 1,
+            }
+        })
+        .sum()
+}
+
+// "NWSE" is one spin cycle
+fn parse_tilts(sequence: &str) -> Vec<Direction> {
+    sequence
+        .chars()
+        .map(|c| match c {
+            'N' => Direction::North,
+            'S' => Direction::South,
+            'W' => Direction::West,
+            'E' => Direction::East,
+            _ => panic!("invalid direction {}", c),
+        })
+        .collect()
+}
+
+fn solve_part1(platform: &Platform) -> usize {
+    let tilted = tilt_platform(platform, Direction::North);
+    platform_load(&tilted, Direction::North)
+}
+
+// the platform after tilting in all of `tilts` in turn, `n` times over
+// every layout repeats eventually as there are only finitely many, but the lead-in before
+// the cycle can be long, so the rounds are not remembered: Brent's cycle detection keeps only
+// the tortoise, which jumps to the hare whenever the hare is a power of two rounds ahead
+// hashes are compared every round and the full rock counts only when they agree
+fn after_tilts(platform: &Platform, tilts: &[Direction], n: usize) -> Platform {
+    let mut platform = platform.clone();
+    if n == 0 || tilts.is_empty() {
+        return platform;
+    }
+    let tilter = Tilter::new(&platform);
+    let mut spinner = Spinner::new(&tilter, tilts, &platform);
+    let mut tortoise = (spinner.hash, spinner.state().clone());
+    // rounds the hare is ahead of the tortoise
+    let (mut power, mut period) = (1, 0);
+    let mut i = 1;
+    while i < n {
+        spinner.round();
+        i += 1;
+        period += 1;
+        if spinner.hash == tortoise.0 && *spinner.state() == tortoise.1 {
+            // the state repeats every `period` rounds, only the remainder matters
+            for _ in 0..(n - i) % period {
+                spinner.round();
+            }
             break;
-        } else {
-            last_cycle_platform = platform;
         }
-        i += 1;
+        if period == power {
+            tortoise = (spinner.hash, spinner.state().clone());
+            power *= 2;
+            period = 0;
+        }
     }
-    platform_load(&last_cycle_platform, Direction::North)
+    tilter.unpack(spinner.state(), &mut platform);
+    platform
+}
+
+fn solve_part2(platform: &Platform) -> usize {
+    let platform = after_tilts(platform, &CYCLE, 1000000000);
+    platform_load(&platform, Direction::North)
 }
 
 fn main() {
     let input = include_str!("../input");
     let platform = parse_input(input);
 
+    // `tilt <sequence> <n>` prints the platform after tilting along the sequence n times
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("tilt") {
+        let sequence = args
+            .get(2)
+            .filter(|s| !s.is_empty() && s.chars().all(|c| "NSWE".contains(c)));
+        let n = args.get(3).and_then(|n| n.parse::<usize>().ok());
+        let (Some(sequence), Some(n)) = (sequence, n) else {
+            eprintln!("usage: day14 tilt <sequence of N, S, W and E> <n>");
+            return;
+        };
+        let tilts = parse_tilts(sequence);
+        let platform = after_tilts(&platform, &tilts, n);
+        print!("{}", platform);
+        for direction in parse_tilts("NSWE") {
+            println!(
+                "{:?} load: {}",
+                direction,
+                platform_load(&platform, direction)
+            );
+        }
+        return;
+    }
+
     println!("Part 1: {}", solve_part1(&platform));
     println!("Part 2: {}", solve_part2(&platform));
 }
@@ -259,6 +560,10 @@ mod tests {
 
     use super::*;
 
+    fn cycle(platform: &Platform) -> Platform {
+        after_tilts(platform, &CYCLE, 1)
+    }
+
     const EXAMPLE: &str = "O....#....
 O.OO#....#
 .....##...
@@ -374,4 +679,186 @@ O.#..O.#.#
         let input = example_parsed!();
         assert_eq!(solve_part2(&input), 64);
     }
+
+    #[test]
+    fn test_segments() {
+        let platform = example_parsed!();
+        let tilter = Tilter::new(&platform);
+        // the first column is split by the cube rocks in its last two rows
+        assert_eq!(
+            tilter.columns.segments[0],
+            Segment {
+                start: 0,
+                len: 8,
+                stride: 10
+            }
+        );
+        // the first row is split at its only cube rock
+        assert_eq!(
+            tilter.rows.segments[..2],
+            [
+                Segment {
+                    start: 0,
+                    len: 5,
+                    stride: 1
+                },
+                Segment {
+                    start: 6,
+                    len: 4,
+                    stride: 1
+                }
+            ]
+        );
+        let tiles = platform.tiles.iter().filter(|&t| *t != C).count();
+        assert_eq!(
+            tilter.rows.segments.iter().map(|s| s.len).sum::<usize>(),
+            tiles
+        );
+        assert_eq!(
+            tilter.columns.segments.iter().map(|s| s.len).sum::<usize>(),
+            tiles
+        );
+    }
+
+    #[test]
+    fn test_after_tilts() {
+        let platform = example_parsed!();
+        assert_eq!(after_tilts(&platform, &CYCLE, 0), platform);
+        assert_eq!(
+            after_tilts(&platform, &CYCLE, 3),
+            parse_input(EXAMPLE_3_CYCLE)
+        );
+        // the example repeats every 7 cycles
+        assert_eq!(
+            after_tilts(&platform, &CYCLE, 1000),
+            after_tilts(&platform, &CYCLE, 20)
+        );
+    }
+
+    // the example repeated 100 times in both directions
+    fn tiled_example() -> Platform {
+        let mut input = String::new();
+        for _ in 0..100 {
+            for line in EXAMPLE.lines() {
+                input.push_str(&line.repeat(100));
+                input.push('\n');
+            }
+        }
+        parse_input(&input)
+    }
+
+    // tilting every tile without any of the bookkeeping
+    fn naive_cycles(platform: &Platform, n: usize) -> Platform {
+        let mut platform = platform.clone();
+        for _ in 0..n {
+            for direction in CYCLE {
+                platform = tilt_platform(&platform, direction);
+            }
+        }
+        platform
+    }
+
+    #[test]
+    fn test_incremental_cycles() {
+        let platform = example_parsed!();
+        for n in 0..30 {
+            assert_eq!(
+                after_tilts(&platform, &CYCLE, n),
+                naive_cycles(&platform, n),
+                "after {} cycles",
+                n
+            );
+        }
+        // rocks leave and enter segments in the middle of the tiled platform as well
+        let tiled = tiled_example();
+        let mut input = String::new();
+        for y in 0..40 {
+            let row = &tiled.tiles[y * 1000 + 3..y * 1000 + 43];
+            input.extend(row.iter().map(|t| t.to_string()));
+            input.push('\n');
+        }
+        let platform = parse_input(&input);
+        for n in [1, 2, 5, 17, 60] {
+            assert_eq!(
+                after_tilts(&platform, &CYCLE, n),
+                naive_cycles(&platform, n),
+                "after {} cycles",
+                n
+            );
+        }
+    }
+
+    #[test]
+    fn test_large_platform() {
+        let platform = tiled_example();
+        assert_eq!((platform.width, platform.height), (1000, 1000));
+        let spun = after_tilts(&platform, &CYCLE, 1000000000);
+        let rocks = |p: &Platform| p.tiles.iter().filter(|&t| *t == O).count();
+        assert_eq!(rocks(&spun), rocks(&platform));
+        // the state one cycle later is the one for a count one higher
+        assert_eq!(cycle(&spun), after_tilts(&platform, &CYCLE, 1000000001));
+    }
+
+    #[test]
+    fn test_parse_tilts() {
+        use Direction::*;
+        assert_eq!(parse_tilts("NNE"), vec![North, North, East]);
+        assert_eq!(parse_tilts("NWSE"), CYCLE);
+        assert_eq!(parse_tilts(""), vec![]);
+    }
+
+    #[test]
+    fn test_custom_sequences() {
+        let platform = example_parsed!();
+        for sequence in ["N", "NNE", "EWS", "SSWN", "WE", "NWSE"] {
+            let tilts = parse_tilts(sequence);
+            let naive = |n: usize| {
+                let mut naive = platform.clone();
+                for _ in 0..n {
+                    for &direction in &tilts {
+                        naive = tilt_platform(&naive, direction);
+                    }
+                }
+                naive
+            };
+            for n in 0..15 {
+                assert_eq!(
+                    after_tilts(&platform, &tilts, n),
+                    naive(n),
+                    "{} {} times",
+                    sequence,
+                    n
+                );
+            }
+            let spun = after_tilts(&platform, &tilts, 1000000000);
+            assert_eq!(
+                after_tilts(&spun, &tilts, 1),
+                after_tilts(&platform, &tilts, 1000000001)
+            );
+        }
+        // tilting the same way again changes nothing
+        assert_eq!(
+            after_tilts(&platform, &parse_tilts("NNE"), 1000000000),
+            after_tilts(&platform, &parse_tilts("NE"), 1000000000)
+        );
+        assert_eq!(after_tilts(&platform, &[], 5), platform);
+    }
+
+    #[test]
+    fn test_platform_load() {
+        let platform = parse_input("O..\n..O\n...\n.O.\n");
+        assert_eq!(platform_load(&platform, Direction::North), 4 + 3 + 1);
+        assert_eq!(platform_load(&platform, Direction::South), 1 + 2 + 4);
+        assert_eq!(platform_load(&platform, Direction::West), 3 + 1 + 2);
+        assert_eq!(platform_load(&platform, Direction::East), 1 + 3 + 2);
+        let tilted = tilt_platform(&example_parsed!(), Direction::North);
+        assert_eq!(platform_load(&tilted, Direction::North), 136);
+        // after the first cycle the rocks lie against the east edge
+        let platform = after_tilts(&example_parsed!(), &CYCLE, 1);
+        assert_eq!(platform_load(&platform, Direction::North), 87);
+        assert_eq!(
+            platform_load(&platform, Direction::East) + platform_load(&platform, Direction::West),
+            18 * 11
+        );
+    }
 }